@@ -10,9 +10,9 @@
 pub mod math;
 pub mod render;
 
-use math::{color::Color, vector::*};
+use math::{color::Color, transform::Transform2D, vector::*};
 use render::{
-    cam,
+    cam, framebuffer,
     ogl::*,
     shaders,
     shapes::{Circle, Square, Triangle},
@@ -21,44 +21,44 @@ use render::{
 use std::{any::Any, cell::RefCell, rc::Rc};
 
 #[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
 fn test_window() {
-    let mut window = Window::new("Game", 800, 800);
+    // one frame offscreen, then check a few pixels and quit
+    let image = render::golden::render(800, 800, Color::rgb(100, 149, 237), || {
+        let mut draws = Vec::<(DrawStream, Material)>::new();
 
-    let mut draws = Vec::<(DrawStream, Material)>::new();
+        let mut sq = Square::new(vec2(-0.25, -0.9), vec2(0.5, 0.5));
+        let d1 = DrawStream::from(sq);
 
-    let mut sq = Square::new(vec2(-0.25, -0.9), vec2(0.5, 0.5));
-    let d1 = DrawStream::from(sq);
+        let mut ci = Circle::new(vec2(0., 0.3), 0.3);
+        let d2 = DrawStream::from(ci);
 
-    let mut ci = Circle::new(vec2(0., 0.3), 0.3);
-    let d2 = DrawStream::from(ci);
+        let mut tri = Triangle::new(vec2(0., 0.9), vec2(-0.5, 0.4), vec2(0.5, 0.4));
+        let d3 = DrawStream::from(tri);
 
-    let mut tri = Triangle::new(vec2(0., 0.9), vec2(-0.5, 0.4), vec2(0.5, 0.4));
-    let d3 = DrawStream::from(tri);
+        let mut mat1 = Material::default();
+        mat1.set_color(Color::rgb(255, 255, 0));
 
-    let mut mat1 = Material::default();
-    mat1.set_color(Color::rgb(255, 255, 0));
+        let mut mat2 = Material::default();
+        mat2.set_color(Color::rgb(0, 255, 255));
 
-    let mut mat2 = Material::default();
-    mat2.set_color(Color::rgb(0, 255, 255));
+        let mut mat3 = Material::default();
+        mat3.set_color(Color::rgb(255, 0, 255));
 
-    let mut mat3 = Material::default();
-    mat3.set_color(Color::rgb(255, 0, 255));
+        draws.push((d1, mat1));
+        draws.push((d2, mat2));
+        draws.push((d3, mat3));
 
-    draws.push((d1, mat1));
-    draws.push((d2, mat2));
-    draws.push((d3, mat3));
-
-    while !window.should_close() {
-        while let Some(e) = window.events() {
-            if e.is_key_pressed(Key::Escape) {
-                window.set_should_close(true);
-            }
-        }
-
-        window.clear(Color::rgb(100, 149, 237));
         for d in &draws {
-            d.0.draw(&d.1);
+            d.0.draw(&d.1, &Transform2D::default());
         }
-        window.swap_buffers();
-    }
+    });
+
+    let pixel = |x: u32, y: u32| {
+        let p = image.get_pixel(x, y).0;
+        [p[0], p[1], p[2]]
+    };
+
+    assert_eq!(pixel(5, 5), [100, 149, 237]);
+    assert_eq!(pixel(400, 660), [255, 255, 0]);
 }
@@ -1,3 +1,4 @@
 pub mod color;
 pub mod matrix;
+pub mod transform;
 pub mod vector;
@@ -2,6 +2,7 @@ use crate::render::ogl::Uniform;
 
 use super::vector::vec3;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub format: Format,
 }
@@ -27,6 +28,14 @@ impl Color {
         }
     }
 
+    // 0 to 1 floats for vertex data, rgb colors are fully opaque
+    pub fn to_rgba_f32(&self) -> [f32; 4] {
+        match self.format {
+            Format::RGBA { r, g, b, a } => [r, g, b, a].map(|c| c as f32 / 255.0),
+            Format::RGB { r, g, b } => [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0],
+        }
+    }
+
     // pub fn asdad(&self) -> u32 {
     //     match self.format {
     //         Format::RGBA { r, g, b, a } => {
@@ -40,10 +49,10 @@ impl Color {
     // }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Format {
     RGBA { r: u8, g: u8, b: u8, a: u8 },
     RGB { r: u8, g: u8, b: u8 },
-    SRGB,
 }
 
 impl Uniform for Color {
@@ -58,7 +67,6 @@ impl Uniform for Color {
                     let v = vec3(r as f32 / 255f32, g as f32 / 255f32, b as f32 / 255f32);
                     gl::Uniform3fv(loc, 1, v.as_array().as_ptr());
                 }
-                Format::SRGB => todo!(),
             }
         }
     }
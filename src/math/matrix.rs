@@ -1,12 +1,240 @@
+use std::ops::{Mul, MulAssign};
+
 use crate::math::vector::*;
+use crate::render::ogl::Uniform;
 
 pub type Mat2d = Matrix<f32, 3, 3>;
 pub type Mat3d = Matrix<f32, 4, 4>;
 
+// column major like opengl wants it, v[column][row]
 pub struct Matrix<T, const C: usize, const R: usize> {
     v: [Vector<T, R>; C],
 }
 
-impl<T, const C: usize, const R: usize> Matrix<T, C, R> {
-    
+// **********
+// **********
+//    Impls
+// **********
+// **********
+
+impl<T, const C: usize, const R: usize> Clone for Matrix<T, C, R>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self { v: self.v.clone() }
+    }
+}
+impl<T, const C: usize, const R: usize> Copy for Matrix<T, C, R> where T: Copy {}
+
+impl<T, const C: usize, const R: usize> std::fmt::Debug for Matrix<T, C, R>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Matrix").field("v", &self.v).finish()
+    }
+}
+
+impl<const N: usize> Matrix<f32, N, N> {
+    pub fn identity() -> Self {
+        Self::from_cols(std::array::from_fn(|c| {
+            std::array::from_fn(|r| if c == r { 1. } else { 0. })
+        }))
+    }
+
+    pub fn from_cols(cols: [[f32; N]; N]) -> Self {
+        Self {
+            v: cols.map(Vector::from),
+        }
+    }
+
+    pub fn to_cols(&self) -> [[f32; N]; N] {
+        self.v.map(|col| *col.as_array())
+    }
+
+    pub fn get(&self, column: usize, row: usize) -> f32 {
+        self.v[column][row]
+    }
+
+    pub fn transpose(&self) -> Self {
+        Self::from_cols(std::array::from_fn(|c| {
+            std::array::from_fn(|r| self.v[r][c])
+        }))
+    }
+}
+
+impl<const N: usize> Default for Matrix<f32, N, N> {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl<const N: usize> Mul for Matrix<f32, N, N> {
+    type Output = Self;
+
+    // self * rhs applies rhs first
+    fn mul(self, rhs: Self) -> Self {
+        Self::from_cols(std::array::from_fn(|c| {
+            std::array::from_fn(|r| (0..N).map(|k| self.v[k][r] * rhs.v[c][k]).sum())
+        }))
+    }
+}
+
+impl<const N: usize> MulAssign for Matrix<f32, N, N> {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<const N: usize> Mul<Vector<f32, N>> for Matrix<f32, N, N> {
+    type Output = Vector<f32, N>;
+
+    fn mul(self, rhs: Vector<f32, N>) -> Vector<f32, N> {
+        Vector::from(std::array::from_fn(|r| {
+            (0..N).map(|c| self.v[c][r] * rhs[c]).sum()
+        }))
+    }
+}
+
+// a 2d affine transform, the last column is the translation
+impl Mat2d {
+    pub fn translation(t: Vec2) -> Self {
+        Self::from_cols([[1., 0., 0.], [0., 1., 0.], [t[X], t[Y], 1.]])
+    }
+
+    // counter clockwise in radians
+    pub fn rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::from_cols([[cos, sin, 0.], [-sin, cos, 0.], [0., 0., 1.]])
+    }
+
+    pub fn scale(s: Vec2) -> Self {
+        Self::from_cols([[s[X], 0., 0.], [0., s[Y], 0.], [0., 0., 1.]])
+    }
+
+    pub fn transform_point(&self, p: Vec2) -> Vec2 {
+        let v = *self * vec3(p[X], p[Y], 1.);
+        vec2(v[X], v[Y])
+    }
+}
+
+impl Mat3d {
+    pub fn translation(t: Vec3) -> Self {
+        Self::from_cols([
+            [1., 0., 0., 0.],
+            [0., 1., 0., 0.],
+            [0., 0., 1., 0.],
+            [t[X], t[Y], t[Z], 1.],
+        ])
+    }
+
+    pub fn rotation_x(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::from_cols([
+            [1., 0., 0., 0.],
+            [0., cos, sin, 0.],
+            [0., -sin, cos, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn rotation_y(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::from_cols([
+            [cos, 0., -sin, 0.],
+            [0., 1., 0., 0.],
+            [sin, 0., cos, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn rotation_z(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::from_cols([
+            [cos, sin, 0., 0.],
+            [-sin, cos, 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn scale(s: Vec3) -> Self {
+        Self::from_cols([
+            [s[X], 0., 0., 0.],
+            [0., s[Y], 0., 0.],
+            [0., 0., s[Z], 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        let v = *self * vec4(p[X], p[Y], p[Z], 1.);
+        vec3(v[X], v[Y], v[Z])
+    }
+}
+
+// a 2d transform in the xy plane, z is left alone
+impl From<Mat2d> for Mat3d {
+    fn from(m: Mat2d) -> Self {
+        Self::from_cols([
+            [m.get(0, 0), m.get(0, 1), 0., 0.],
+            [m.get(1, 0), m.get(1, 1), 0., 0.],
+            [0., 0., 1., 0.],
+            [m.get(2, 0), m.get(2, 1), 0., 1.],
+        ])
+    }
+}
+
+// *******************
+// *******************
+//   Uniform Impls
+// *******************
+// *******************
+
+impl Uniform for Mat2d {
+    fn apply_uniform(&mut self, loc: i32) {
+        let cols = self.to_cols();
+        unsafe { gl::UniformMatrix3fv(loc, 1, gl::FALSE, cols.as_ptr().cast()) }
+    }
+}
+
+impl Uniform for Mat3d {
+    fn apply_uniform(&mut self, loc: i32) {
+        let cols = self.to_cols();
+        unsafe { gl::UniformMatrix4fv(loc, 1, gl::FALSE, cols.as_ptr().cast()) }
+    }
+}
+
+// *********
+// *********
+//   Tests
+// *********
+// *********
+
+#[cfg(test)]
+fn close(a: Vec2, x: f32, y: f32) -> bool {
+    (a[X] - x).abs() < 1e-5 && (a[Y] - y).abs() < 1e-5
+}
+
+#[test]
+fn test_mat_mul() {
+    let m = Mat2d::translation(vec2(5., 0.)) * Mat2d::rotation(std::f32::consts::FRAC_PI_2);
+
+    // rotated first, then moved
+    assert!(close(m.transform_point(vec2(1., 0.)), 5., 1.));
+    assert!(close(
+        (Mat2d::identity() * m).transform_point(vec2(0., 2.)),
+        3.,
+        0.
+    ));
+
+    let m3 = Mat3d::from(m);
+    let p = m3.transform_point(vec3(1., 0., 7.));
+    assert!(close(vec2(p[X], p[Y]), 5., 1.));
+    assert_eq!(p[Z], 7.);
+
+    let t = Mat3d::translation(vec3(1., 2., 3.)).transpose();
+    assert_eq!(t.get(0, 3), 1.);
+    assert_eq!(t.get(3, 0), 0.);
 }
@@ -0,0 +1,172 @@
+use crate::math::matrix::*;
+use crate::math::vector::*;
+
+// anything that can place a mesh in the world, draw streams take it as the model matrix
+pub trait Transform {
+    fn matrix(&self) -> Mat3d;
+}
+
+impl Transform for Mat3d {
+    fn matrix(&self) -> Mat3d {
+        *self
+    }
+}
+
+impl Transform for Mat2d {
+    fn matrix(&self) -> Mat3d {
+        Mat3d::from(*self)
+    }
+}
+
+// origin is the point in the mesh that ends up at position, and the one it rotates and
+// scales around
+#[derive(Debug, Clone, Copy)]
+pub struct Transform2D {
+    pub position: Vec2,
+    // counter clockwise in radians
+    pub rotation: f32,
+    pub scale: Vec2,
+    pub origin: Vec2,
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self {
+            position: vec2(0., 0.),
+            rotation: 0.,
+            scale: vec2(1., 1.),
+            origin: vec2(0., 0.),
+        }
+    }
+}
+
+impl Transform2D {
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            position,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec2) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_origin(mut self, origin: Vec2) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn mat2d(&self) -> Mat2d {
+        Mat2d::translation(self.position)
+            * Mat2d::rotation(self.rotation)
+            * Mat2d::scale(self.scale)
+            * Mat2d::translation(vec2(0., 0.) - self.origin)
+    }
+}
+
+impl Transform for Transform2D {
+    fn matrix(&self) -> Mat3d {
+        Mat3d::from(self.mat2d())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Transform3D {
+    pub position: Vec3,
+    // euler angles in radians, applied x then y then z
+    pub rotation: Vec3,
+    pub scale: Vec3,
+    pub origin: Vec3,
+}
+
+impl Default for Transform3D {
+    fn default() -> Self {
+        Self {
+            position: vec3(0., 0., 0.),
+            rotation: vec3(0., 0., 0.),
+            scale: vec3(1., 1., 1.),
+            origin: vec3(0., 0., 0.),
+        }
+    }
+}
+
+impl Transform3D {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Vec3) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_origin(mut self, origin: Vec3) -> Self {
+        self.origin = origin;
+        self
+    }
+}
+
+// the same placement flat in the xy plane, turning around z
+impl From<Transform2D> for Transform3D {
+    fn from(t: Transform2D) -> Self {
+        Self {
+            position: vec3(t.position[X], t.position[Y], 0.),
+            rotation: vec3(0., 0., t.rotation),
+            scale: vec3(t.scale[X], t.scale[Y], 1.),
+            origin: vec3(t.origin[X], t.origin[Y], 0.),
+        }
+    }
+}
+
+impl Transform for Transform3D {
+    fn matrix(&self) -> Mat3d {
+        Mat3d::translation(self.position)
+            * Mat3d::rotation_z(self.rotation[Z])
+            * Mat3d::rotation_y(self.rotation[Y])
+            * Mat3d::rotation_x(self.rotation[X])
+            * Mat3d::scale(self.scale)
+            * Mat3d::translation(vec3(0., 0., 0.) - self.origin)
+    }
+}
+
+#[test]
+fn test_transform_2d() {
+    use std::f32::consts::FRAC_PI_2;
+
+    // a 2x2 square spun a quarter turn around its middle and moved so its middle is at (10, 0)
+    let t = Transform2D::new(vec2(10., 0.))
+        .with_origin(vec2(1., 1.))
+        .with_rotation(FRAC_PI_2)
+        .with_scale(vec2(2., 1.));
+
+    let corner = t.mat2d().transform_point(vec2(2., 1.));
+    assert!((corner[X] - 10.).abs() < 1e-5 && (corner[Y] - 2.).abs() < 1e-5);
+
+    let middle = t.matrix().transform_point(vec3(1., 1., 0.));
+    assert!((middle[X] - 10.).abs() < 1e-5 && middle[Y].abs() < 1e-5);
+}
+
+#[test]
+fn test_transform_3d() {
+    use std::f32::consts::FRAC_PI_2;
+
+    // x then y, so forward ends up pointing down after tipping over and turning
+    let t = Transform3D::new(vec3(0., 0., 5.)).with_rotation(vec3(FRAC_PI_2, FRAC_PI_2, 0.));
+    let p = t.matrix().transform_point(vec3(0., 0., 1.));
+    assert!(p[X].abs() < 1e-5 && (p[Y] + 1.).abs() < 1e-5 && (p[Z] - 5.).abs() < 1e-5);
+}
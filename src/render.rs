@@ -1,8 +1,25 @@
+pub mod animation;
+pub mod atlas;
+pub mod buffer;
 pub mod cam;
+pub mod context;
+pub mod cursor;
 pub mod debug;
+pub mod framebuffer;
+#[cfg(test)]
+pub mod golden;
+#[cfg(feature = "egl")]
+pub mod headless;
+pub mod instance;
 pub mod ogl;
+pub mod postprocess;
+pub mod readback;
+pub mod ring;
+pub mod scene;
 pub mod shaders;
 pub mod shapes;
+pub mod sprite;
+pub mod text;
 pub mod texture;
 pub mod window;
 pub mod image;
@@ -0,0 +1,362 @@
+pub mod aseprite;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::atlas::{AtlasData, SpriteSheet};
+use super::sprite::UvRect;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub uv: UvRect,
+    // seconds
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
+    Loop,
+    // forwards then backwards without repeating the end frames
+    PingPong,
+    // stops on the last frame
+    Once,
+}
+
+#[derive(Debug, Clone)]
+pub struct Clip {
+    pub name: String,
+    pub frames: Vec<Frame>,
+    pub mode: PlayMode,
+    // (frame, event name), fired every time the frame is reached
+    pub events: Vec<(usize, String)>,
+}
+
+impl Clip {
+    pub fn new(name: &str, mode: PlayMode) -> Self {
+        Self {
+            name: name.to_string(),
+            frames: Vec::new(),
+            mode,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn add_frame(&mut self, uv: UvRect, duration: f32) {
+        self.frames.push(Frame { uv, duration });
+    }
+
+    pub fn add_event(&mut self, frame: usize, name: &str) {
+        self.events.push((frame, name.to_string()));
+    }
+
+    // every frame the same length, from a list of atlas region names
+    pub fn from_atlas(
+        name: &str,
+        mode: PlayMode,
+        atlas: &AtlasData,
+        regions: &[&str],
+        frame_duration: f32,
+    ) -> Option<Self> {
+        let mut clip = Self::new(name, mode);
+        for region in regions {
+            clip.add_frame(atlas.regions.get(*region)?.uv, frame_duration);
+        }
+        Some(clip)
+    }
+
+    // frames first to last (inclusive) of a grid sprite sheet, counting across then down
+    pub fn from_sheet(
+        name: &str,
+        mode: PlayMode,
+        sheet: &SpriteSheet,
+        first: usize,
+        last: usize,
+        frame_duration: f32,
+    ) -> Self {
+        let mut clip = Self::new(name, mode);
+        for uv in &sheet.frames[first..=last] {
+            clip.add_frame(*uv, frame_duration);
+        }
+        clip
+    }
+
+    // one pass through the frames, a ping pong cycle is about twice this
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|f| f.duration).sum()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Animation {
+    clips: HashMap<String, Clip>,
+}
+
+impl Animation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_clip(&mut self, clip: Clip) {
+        self.clips.insert(clip.name.clone(), clip);
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&Clip> {
+        self.clips.get(name)
+    }
+
+    pub fn clip_mut(&mut self, name: &str) -> Option<&mut Clip> {
+        self.clips.get_mut(name)
+    }
+
+    pub fn clip_names(&self) -> impl Iterator<Item = &str> {
+        self.clips.keys().map(|k| k.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimationEvent {
+    pub clip: String,
+    pub name: String,
+    pub frame: usize,
+}
+
+// plays one clip at a time, call update with the frame's delta time and read uv after
+pub struct AnimationPlayer {
+    animation: Rc<Animation>,
+    clip: Option<String>,
+    frame: usize,
+    time: f32,
+    // 1 going forwards, -1 on the way back of a ping pong
+    direction: i32,
+    speed: f32,
+    paused: bool,
+    finished: bool,
+    // the first frame's events fire on the first update after play
+    entered: bool,
+}
+
+impl AnimationPlayer {
+    pub fn new(animation: Rc<Animation>) -> Self {
+        Self {
+            animation,
+            clip: None,
+            frame: 0,
+            time: 0.,
+            direction: 1,
+            speed: 1.,
+            paused: false,
+            finished: false,
+            entered: false,
+        }
+    }
+
+    // starts the clip from the beginning, playing the clip that is already playing does nothing
+    pub fn play(&mut self, clip: &str) {
+        if self.clip.as_deref() == Some(clip) && !self.finished {
+            return;
+        }
+        self.restart(clip);
+    }
+
+    pub fn restart(&mut self, clip: &str) {
+        assert!(
+            self.animation.clip(clip).is_some(),
+            "No animation clip named {clip}"
+        );
+
+        self.clip = Some(clip.to_string());
+        self.frame = 0;
+        self.time = 0.;
+        self.direction = 1;
+        self.finished = false;
+        self.entered = false;
+    }
+
+    pub fn stop(&mut self) {
+        self.clip = None;
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    // 2 is double speed, 0.5 is half, negative speeds are treated as 0
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.);
+    }
+
+    pub fn clip(&self) -> Option<&str> {
+        self.clip.as_deref()
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    fn current_clip(&self) -> Option<&Clip> {
+        self.animation.clip(self.clip.as_deref()?)
+    }
+
+    pub fn uv(&self) -> Option<UvRect> {
+        Some(self.current_clip()?.frames.get(self.frame)?.uv)
+    }
+
+    fn enter_frame(&self, clip: &Clip, events: &mut Vec<AnimationEvent>) {
+        for (frame, name) in &clip.events {
+            if *frame == self.frame {
+                events.push(AnimationEvent {
+                    clip: clip.name.clone(),
+                    name: name.clone(),
+                    frame: self.frame,
+                });
+            }
+        }
+    }
+
+    // gives back the events of every frame that was reached, in order
+    pub fn update(&mut self, dt: f32) -> Vec<AnimationEvent> {
+        let mut events = Vec::new();
+
+        let animation = self.animation.clone();
+        let Some(clip) = self.clip.as_deref().and_then(|c| animation.clip(c)) else {
+            return events;
+        };
+
+        if clip.frames.is_empty() {
+            return events;
+        }
+
+        if !self.entered {
+            self.entered = true;
+            self.enter_frame(clip, &mut events);
+        }
+
+        if self.paused || self.finished {
+            return events;
+        }
+
+        self.time += dt * self.speed;
+
+        let last = clip.frames.len() - 1;
+        loop {
+            // a zero length frame would never let the loop finish
+            let duration = clip.frames[self.frame].duration.max(1e-4);
+            if self.time < duration {
+                break;
+            }
+            self.time -= duration;
+
+            match clip.mode {
+                PlayMode::Loop => {
+                    self.frame = if self.frame == last {
+                        0
+                    } else {
+                        self.frame + 1
+                    };
+                }
+                PlayMode::Once => {
+                    if self.frame == last {
+                        self.finished = true;
+                        self.time = 0.;
+                        break;
+                    }
+                    self.frame += 1;
+                }
+                PlayMode::PingPong => {
+                    if last == 0 {
+                        continue;
+                    }
+                    if (self.frame == last && self.direction > 0)
+                        || (self.frame == 0 && self.direction < 0)
+                    {
+                        self.direction = -self.direction;
+                    }
+                    self.frame = (self.frame as i32 + self.direction) as usize;
+                }
+            }
+
+            self.enter_frame(clip, &mut events);
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+fn test_animation() -> Rc<Animation> {
+    let mut animation = Animation::new();
+
+    for (name, mode) in [
+        ("loop", PlayMode::Loop),
+        ("pingpong", PlayMode::PingPong),
+        ("once", PlayMode::Once),
+    ] {
+        let mut clip = Clip::new(name, mode);
+        for i in 0..3 {
+            clip.add_frame(UvRect::new(i as f32, 0., i as f32 + 1., 1.), 0.1);
+        }
+        clip.add_event(0, "start");
+        clip.add_event(2, "end");
+        animation.add_clip(clip);
+    }
+
+    Rc::new(animation)
+}
+
+#[cfg(test)]
+fn play_frames(player: &mut AnimationPlayer, steps: usize) -> Vec<usize> {
+    (0..steps)
+        .map(|_| {
+            player.update(0.1);
+            player.frame()
+        })
+        .collect()
+}
+
+#[test]
+fn test_player_modes() {
+    let animation = test_animation();
+    let mut player = AnimationPlayer::new(animation);
+
+    player.play("loop");
+    player.update(0.0);
+    assert_eq!(play_frames(&mut player, 5), vec![1, 2, 0, 1, 2]);
+
+    player.play("pingpong");
+    player.update(0.0);
+    assert_eq!(play_frames(&mut player, 6), vec![1, 2, 1, 0, 1, 2]);
+
+    player.play("once");
+    player.update(0.0);
+    assert_eq!(play_frames(&mut player, 4), vec![1, 2, 2, 2]);
+    assert!(player.is_finished());
+    assert_eq!(player.uv(), Some(UvRect::new(2., 0., 3., 1.)));
+}
+
+#[test]
+fn test_player_events_and_speed() {
+    let animation = test_animation();
+    let mut player = AnimationPlayer::new(animation);
+
+    player.play("loop");
+    let events = player.update(0.05);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].name, "start");
+
+    // double speed skips through two frames in one update and fires what it passed
+    player.set_speed(2.);
+    let events = player.update(0.1);
+    assert_eq!(player.frame(), 2);
+    assert_eq!(
+        events.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(),
+        vec!["end"]
+    );
+
+    let events = player.update(0.05);
+    assert_eq!(player.frame(), 0);
+    assert_eq!(events[0].name, "start");
+}
@@ -0,0 +1,312 @@
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use serde::de::{Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+
+use super::{Animation, Clip, PlayMode};
+use crate::render::sprite::UvRect;
+
+// reads the json that aseprite writes next to an exported sprite sheet, both the hash and array layouts,
+// every frame tag becomes a clip and a sheet without tags becomes one looping clip called "default"
+
+#[derive(Debug)]
+pub enum AsepriteError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Tag { name: String, message: &'static str },
+}
+
+impl fmt::Display for AsepriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsepriteError::Io(e) => write!(f, "Failed to read aseprite json: {e}"),
+            AsepriteError::Json(e) => write!(f, "Bad aseprite json: {e}"),
+            AsepriteError::Tag { name, message } => {
+                write!(f, "Bad aseprite frame tag \"{name}\": {message}")
+            }
+        }
+    }
+}
+
+impl Error for AsepriteError {}
+
+impl From<std::io::Error> for AsepriteError {
+    fn from(e: std::io::Error) -> Self {
+        AsepriteError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for AsepriteError {
+    fn from(e: serde_json::Error) -> Self {
+        AsepriteError::Json(e)
+    }
+}
+
+#[derive(Deserialize)]
+struct Sheet {
+    #[serde(deserialize_with = "frames_in_order")]
+    frames: Vec<SheetFrame>,
+    meta: Meta,
+}
+
+#[derive(Deserialize)]
+struct SheetFrame {
+    frame: Rect,
+    // milliseconds
+    #[serde(default = "default_duration")]
+    duration: f32,
+}
+
+fn default_duration() -> f32 {
+    100.
+}
+
+#[derive(Deserialize)]
+struct Rect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Meta {
+    size: Size,
+    #[serde(default)]
+    frame_tags: Vec<FrameTag>,
+}
+
+#[derive(Deserialize)]
+struct Size {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct FrameTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: Direction,
+    // how many times the tag plays, aseprite writes it as a string and leaves it out for forever
+    repeat: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Direction {
+    Reverse,
+    Pingpong,
+    PingpongReverse,
+    // newer aseprite versions may add directions, those play forward
+    #[default]
+    #[serde(other)]
+    Forward,
+}
+
+// the hash layout keys every frame by its file name, in the order aseprite wrote them
+fn frames_in_order<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<SheetFrame>, D::Error> {
+    struct Frames;
+
+    impl<'de> Visitor<'de> for Frames {
+        type Value = Vec<SheetFrame>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an array or a map of frames")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut frames = Vec::new();
+            while let Some(frame) = seq.next_element()? {
+                frames.push(frame);
+            }
+            Ok(frames)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut frames = Vec::new();
+            while let Some((_, frame)) = map.next_entry::<IgnoredAny, _>()? {
+                frames.push(frame);
+            }
+            Ok(frames)
+        }
+    }
+
+    deserializer.deserialize_any(Frames)
+}
+
+impl Animation {
+    pub fn from_aseprite_file<P: AsRef<Path>>(path: P) -> Result<Self, AsepriteError> {
+        Self::from_aseprite_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn from_aseprite_json(text: &str) -> Result<Self, AsepriteError> {
+        let sheet: Sheet = serde_json::from_str(text)?;
+        let Size {
+            w: sheet_width,
+            h: sheet_height,
+        } = sheet.meta.size;
+
+        let make_clip = |name: &str, mode: PlayMode, indices: &[usize]| {
+            let mut clip = Clip::new(name, mode);
+            for &i in indices {
+                let f = &sheet.frames[i];
+                let Rect { x, y, w, h } = f.frame;
+                clip.add_frame(
+                    UvRect::from_pixels(x, y, w, h, sheet_width, sheet_height),
+                    f.duration / 1000.,
+                );
+            }
+            clip
+        };
+
+        let mut animation = Animation::new();
+        if sheet.meta.frame_tags.is_empty() {
+            let all: Vec<usize> = (0..sheet.frames.len()).collect();
+            animation.add_clip(make_clip("default", PlayMode::Loop, &all));
+        }
+
+        for tag in &sheet.meta.frame_tags {
+            let bad = |message| AsepriteError::Tag {
+                name: tag.name.clone(),
+                message,
+            };
+            if tag.from > tag.to || tag.to >= sheet.frames.len() {
+                return Err(bad("its frames are not in the sheet"));
+            }
+            let repeat = match &tag.repeat {
+                Some(repeat) => repeat
+                    .parse::<usize>()
+                    .map_err(|_| bad("repeat is not a number"))?,
+                None => 0,
+            };
+
+            let mut pass: Vec<usize> = (tag.from..=tag.to).collect();
+            if matches!(
+                tag.direction,
+                Direction::Reverse | Direction::PingpongReverse
+            ) {
+                pass.reverse();
+            }
+            let pingpong = matches!(
+                tag.direction,
+                Direction::Pingpong | Direction::PingpongReverse
+            );
+
+            let clip = match repeat {
+                0 if pingpong => make_clip(&tag.name, PlayMode::PingPong, &pass),
+                0 => make_clip(&tag.name, PlayMode::Loop, &pass),
+                // a set number of passes plays through all of them once, aseprite counts each
+                // way of a ping pong as a pass and doesnt show the frame it turns on twice
+                n => {
+                    let mut frames = pass.clone();
+                    for _ in 1..n {
+                        if pingpong {
+                            pass.reverse();
+                            frames.extend_from_slice(&pass[1..]);
+                        } else {
+                            frames.extend_from_slice(&pass);
+                        }
+                    }
+                    make_clip(&tag.name, PlayMode::Once, &frames)
+                }
+            };
+            animation.add_clip(clip);
+        }
+
+        Ok(animation)
+    }
+}
+
+#[test]
+fn test_aseprite_import() {
+    let hash = r#"{
+        "frames": {
+            "run 0.aseprite": { "frame": { "x": 0, "y": 0, "w": 16, "h": 16 }, "duration": 100 },
+            "run 1.aseprite": { "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "duration": 50 },
+            "run 2.aseprite": { "frame": { "x": 32, "y": 0, "w": 16, "h": 16 }, "duration": 100 },
+            "run 3.aseprite": { "frame": { "x": 48, "y": 0, "w": 16, "h": 16 }, "duration": 100 }
+        },
+        "meta": {
+            "size": { "w": 64, "h": 16 },
+            "frameTags": [
+                { "name": "walk", "from": 0, "to": 1, "direction": "forward" },
+                { "name": "back", "from": 1, "to": 3, "direction": "reverse" },
+                { "name": "bounce", "from": 2, "to": 3, "direction": "pingpong" }
+            ]
+        }
+    }"#;
+    let animation = Animation::from_aseprite_json(hash).unwrap();
+
+    let walk = animation.clip("walk").unwrap();
+    assert_eq!(walk.mode, PlayMode::Loop);
+    assert_eq!(walk.frames.len(), 2);
+    assert_eq!(walk.frames[1].duration, 0.05);
+    assert_eq!(walk.frames[1].uv, UvRect::new(0.25, 0., 0.5, 1.));
+
+    let back = animation.clip("back").unwrap();
+    assert_eq!(back.frames[0].uv, UvRect::new(0.75, 0., 1., 1.));
+    assert_eq!(back.frames[2].uv, UvRect::new(0.25, 0., 0.5, 1.));
+
+    assert_eq!(animation.clip("bounce").unwrap().mode, PlayMode::PingPong);
+
+    let array = r#"{
+        "frames": [
+            { "filename": "0", "frame": { "x": 0, "y": 0, "w": 8, "h": 8 }, "duration": 200 },
+            { "filename": "1", "frame": { "x": 8, "y": 0, "w": 8, "h": 8 }, "duration": 200 }
+        ],
+        "meta": { "size": { "w": 16, "h": 8 } }
+    }"#;
+    let animation = Animation::from_aseprite_json(array).unwrap();
+    let clip = animation.clip("default").unwrap();
+    assert_eq!(clip.frames.len(), 2);
+    assert_eq!(clip.duration(), 0.4);
+}
+
+#[test]
+fn test_aseprite_repeat() {
+    let json = r#"{
+        "frames": [
+            { "frame": { "x": 0, "y": 0, "w": 8, "h": 8 } },
+            { "frame": { "x": 8, "y": 0, "w": 8, "h": 8 } },
+            { "frame": { "x": 16, "y": 0, "w": 8, "h": 8 } }
+        ],
+        "meta": {
+            "size": { "w": 24, "h": 8 },
+            "frameTags": [
+                { "name": "hit", "from": 0, "to": 2, "direction": "forward", "repeat": "1" },
+                { "name": "twice", "from": 0, "to": 1, "direction": "reverse", "repeat": "2" },
+                { "name": "wobble", "from": 0, "to": 2, "direction": "pingpong", "repeat": "3" },
+                { "name": "idle", "from": 0, "to": 2, "direction": "pingpong", "repeat": "0" }
+            ]
+        }
+    }"#;
+    let animation = Animation::from_aseprite_json(json).unwrap();
+    let order = |name| {
+        let clip = animation.clip(name).unwrap();
+        let frames = clip.frames.iter().map(|f| (f.uv.u0 * 3.).round() as usize);
+        (clip.mode, frames.collect::<Vec<_>>())
+    };
+
+    assert_eq!(order("hit"), (PlayMode::Once, vec![0, 1, 2]));
+    assert_eq!(order("twice"), (PlayMode::Once, vec![1, 0, 1, 0]));
+    assert_eq!(order("wobble"), (PlayMode::Once, vec![0, 1, 2, 1, 0, 1, 2]));
+    assert_eq!(order("idle"), (PlayMode::PingPong, vec![0, 1, 2]));
+    assert_eq!(animation.clip("hit").unwrap().frames[0].duration, 0.1);
+
+    let bad = json.replace(r#""repeat": "1""#, r#""repeat": "often""#);
+    assert!(matches!(
+        Animation::from_aseprite_json(&bad),
+        Err(AsepriteError::Tag { .. })
+    ));
+    assert!(matches!(
+        Animation::from_aseprite_json("{\"frames\": [],}"),
+        Err(AsepriteError::Json(_))
+    ));
+}
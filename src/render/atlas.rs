@@ -0,0 +1,619 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use image::{GenericImage, GenericImageView, ImageError, RgbaImage};
+
+use super::readback::save_image;
+use super::sprite::UvRect;
+use super::texture::Texture;
+
+#[derive(Debug)]
+pub enum AtlasError {
+    // the image plus padding and extrusion is bigger than a whole page
+    TooLarge {
+        name: String,
+        width: u32,
+        height: u32,
+    },
+    DuplicateName(String),
+    Image(ImageError),
+    Manifest {
+        line: usize,
+        message: String,
+    },
+}
+
+impl Display for AtlasError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AtlasError::TooLarge {
+                name,
+                width,
+                height,
+            } => write!(f, "{name} ({width}x{height}) does not fit on an atlas page"),
+            AtlasError::DuplicateName(name) => write!(f, "{name} was added to the atlas twice"),
+            AtlasError::Image(e) => write!(f, "{e}"),
+            AtlasError::Manifest { line, message } => {
+                write!(f, "Bad atlas manifest on line {line}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+impl From<ImageError> for AtlasError {
+    fn from(e: ImageError) -> Self {
+        AtlasError::Image(e)
+    }
+}
+
+impl From<std::io::Error> for AtlasError {
+    fn from(e: std::io::Error) -> Self {
+        AtlasError::Image(ImageError::IoError(e))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedRect {
+    pub x: u32,
+    pub y: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SkylineNode {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+// skyline bottom left packing, keeps track of the top edge of everything placed so far
+// and puts each rect where its bottom edge ends up the highest
+pub struct SkylinePacker {
+    width: u32,
+    height: u32,
+    skyline: Vec<SkylineNode>,
+}
+
+impl SkylinePacker {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            skyline: vec![SkylineNode { x: 0, y: 0, width }],
+        }
+    }
+
+    // the y a rect would sit at if its left edge was on node index
+    fn fit(&self, index: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.skyline[index].x;
+        if x + width > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut left = width as i64;
+        let mut i = index;
+        while left > 0 {
+            let node = self.skyline.get(i)?;
+            y = y.max(node.y);
+            if y + height > self.height {
+                return None;
+            }
+            left -= node.width as i64;
+            i += 1;
+        }
+
+        Some(y)
+    }
+
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<PackedRect> {
+        if width == 0 || height == 0 {
+            return Some(PackedRect { x: 0, y: 0 });
+        }
+
+        // lowest bottom edge wins, then the narrowest node so gaps get filled first
+        let (index, y) = (0..self.skyline.len())
+            .filter_map(|i| self.fit(i, width, height).map(|y| (i, y)))
+            .min_by_key(|&(i, y)| (y + height, self.skyline[i].width))?;
+
+        let x = self.skyline[index].x;
+        self.skyline.insert(
+            index,
+            SkylineNode {
+                x,
+                y: y + height,
+                width,
+            },
+        );
+
+        // everything the new node covers gets cut back or removed
+        let mut i = index + 1;
+        while i < self.skyline.len() {
+            let prev_end = self.skyline[i - 1].x + self.skyline[i - 1].width;
+            let node = &mut self.skyline[i];
+            if node.x >= prev_end {
+                break;
+            }
+
+            let shrink = prev_end - node.x;
+            if node.width <= shrink {
+                self.skyline.remove(i);
+            } else {
+                node.x += shrink;
+                node.width -= shrink;
+                break;
+            }
+        }
+
+        // neighbours at the same height become one node
+        let mut i = 0;
+        while i + 1 < self.skyline.len() {
+            if self.skyline[i].y == self.skyline[i + 1].y {
+                self.skyline[i].width += self.skyline[i + 1].width;
+                self.skyline.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+
+        Some(PackedRect { x, y })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    pub page: usize,
+    // in pixels, not counting the padding and extrusion around it
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub uv: UvRect,
+}
+
+// the packed pages on the cpu, can be saved and loaded so packing happens once at build time
+pub struct AtlasData {
+    pub pages: Vec<RgbaImage>,
+    pub regions: HashMap<String, AtlasRegion>,
+}
+
+impl AtlasData {
+    const HEADER: &'static str = "qrgl-atlas 1";
+
+    // writes <dir>/<stem>.atlas and a <dir>/<stem>_<page>.png for every page
+    pub fn save<P: AsRef<Path>>(&self, dir: P, stem: &str) -> Result<PathBuf, AtlasError> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+
+        let mut manifest = format!("{}\n", Self::HEADER);
+
+        for (i, page) in self.pages.iter().enumerate() {
+            let file = format!("{stem}_{i}.png");
+            save_image(page, dir.join(&file))?;
+            manifest += &format!("page\t{i}\t{}\t{}\t{file}\n", page.width(), page.height());
+        }
+
+        // sorted so the file doesnt change between builds when nothing else did
+        let mut names = self.regions.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            let r = &self.regions[name];
+            manifest += &format!(
+                "region\t{}\t{}\t{}\t{}\t{}\t{name}\n",
+                r.page, r.x, r.y, r.width, r.height
+            );
+        }
+
+        let path = dir.join(format!("{stem}.atlas"));
+        std::fs::write(&path, manifest)?;
+        Ok(path)
+    }
+
+    pub fn load<P: AsRef<Path>>(manifest: P) -> Result<Self, AtlasError> {
+        let manifest = manifest.as_ref();
+        let dir = manifest.parent().unwrap_or(Path::new("."));
+        let text = std::fs::read_to_string(manifest)?;
+
+        let bad = |line: usize, message: &str| AtlasError::Manifest {
+            line: line + 1,
+            message: message.to_string(),
+        };
+
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, header)) if header == Self::HEADER => {}
+            _ => return Err(bad(0, "missing the qrgl-atlas header")),
+        }
+
+        let mut pages = Vec::new();
+        let mut regions = HashMap::new();
+
+        for (n, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            // the region name is always last so it can have spaces or tabs in it
+            let fields = line.splitn(7, '\t').collect::<Vec<_>>();
+            let num = |i: usize| -> Result<u32, AtlasError> {
+                fields
+                    .get(i)
+                    .and_then(|f| f.parse().ok())
+                    .ok_or_else(|| bad(n, &format!("field {i} is not a number")))
+            };
+
+            match fields[0] {
+                "page" if fields.len() == 5 => {
+                    if num(1)? as usize != pages.len() {
+                        return Err(bad(n, "pages are out of order"));
+                    }
+                    pages.push(image::open(dir.join(fields[4]))?.into_rgba8());
+                }
+                "region" if fields.len() == 7 => {
+                    let page = num(1)? as usize;
+                    let (x, y, width, height) = (num(2)?, num(3)?, num(4)?, num(5)?);
+                    let image = pages
+                        .get(page)
+                        .ok_or_else(|| bad(n, "region is on a page that doesnt exist"))?;
+
+                    regions.insert(
+                        fields[6].to_string(),
+                        AtlasRegion {
+                            page,
+                            x,
+                            y,
+                            width,
+                            height,
+                            uv: UvRect::from_pixels(
+                                x,
+                                y,
+                                width,
+                                height,
+                                image.width(),
+                                image.height(),
+                            ),
+                        },
+                    );
+                }
+                _ => return Err(bad(n, "expected a page or region line")),
+            }
+        }
+
+        Ok(Self { pages, regions })
+    }
+}
+
+pub struct TextureAtlasBuilder {
+    page_width: u32,
+    page_height: u32,
+    padding: u32,
+    extrude: u32,
+    images: Vec<(String, RgbaImage)>,
+}
+
+impl TextureAtlasBuilder {
+    pub fn new(page_width: u32, page_height: u32) -> Self {
+        Self {
+            page_width,
+            page_height,
+            padding: 1,
+            extrude: 0,
+            images: Vec::new(),
+        }
+    }
+
+    // empty pixels left between images
+    pub fn set_padding(&mut self, padding: u32) {
+        self.padding = padding;
+    }
+
+    // repeats the edge pixels of each image outwards so linear filtering doesnt bleed in the neighbours
+    pub fn set_extrude(&mut self, extrude: u32) {
+        self.extrude = extrude;
+    }
+
+    pub fn add_image(&mut self, name: &str, image: RgbaImage) {
+        self.images.push((name.to_string(), image));
+    }
+
+    // named after the file without its extension
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), AtlasError> {
+        let path = path.as_ref();
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.add_image(&name, image::open(path)?.into_rgba8());
+        Ok(())
+    }
+
+    // cuts a sprite sheet into cells named <prefix>_0, <prefix>_1.. going across then down
+    pub fn add_grid(&mut self, prefix: &str, sheet: &RgbaImage, cell_width: u32, cell_height: u32) {
+        for (i, cell) in slice_grid(sheet, cell_width, cell_height)
+            .into_iter()
+            .enumerate()
+        {
+            self.add_image(&format!("{prefix}_{i}"), cell);
+        }
+    }
+
+    pub fn build(mut self) -> Result<AtlasData, AtlasError> {
+        let border = self.extrude * 2 + self.padding;
+
+        // tallest first packs a lot tighter with a skyline
+        self.images
+            .sort_by_key(|(_, img)| std::cmp::Reverse((img.height(), img.width())));
+
+        let mut packers = Vec::<SkylinePacker>::new();
+        let mut pages = Vec::<RgbaImage>::new();
+        let mut regions = HashMap::new();
+
+        for (name, image) in self.images {
+            if regions.contains_key(&name) {
+                return Err(AtlasError::DuplicateName(name));
+            }
+
+            let (w, h) = image.dimensions();
+            let (alloc_w, alloc_h) = (w + border, h + border);
+
+            let placed = packers
+                .iter_mut()
+                .enumerate()
+                .find_map(|(i, p)| p.pack(alloc_w, alloc_h).map(|r| (i, r)));
+
+            let (page, rect) = match placed {
+                Some(placed) => placed,
+                None => {
+                    let mut packer = SkylinePacker::new(self.page_width, self.page_height);
+                    let rect = packer.pack(alloc_w, alloc_h).ok_or(AtlasError::TooLarge {
+                        name: name.clone(),
+                        width: w,
+                        height: h,
+                    })?;
+                    packers.push(packer);
+                    pages.push(RgbaImage::new(self.page_width, self.page_height));
+                    (packers.len() - 1, rect)
+                }
+            };
+
+            let (x, y) = (rect.x + self.extrude, rect.y + self.extrude);
+            blit_extruded(&mut pages[page], &image, x, y, self.extrude);
+
+            regions.insert(
+                name,
+                AtlasRegion {
+                    page,
+                    x,
+                    y,
+                    width: w,
+                    height: h,
+                    uv: UvRect::from_pixels(x, y, w, h, self.page_width, self.page_height),
+                },
+            );
+        }
+
+        Ok(AtlasData { pages, regions })
+    }
+}
+
+fn blit_extruded(page: &mut RgbaImage, image: &RgbaImage, x: u32, y: u32, extrude: u32) {
+    let (w, h) = image.dimensions();
+    if w == 0 || h == 0 {
+        return;
+    }
+
+    let e = extrude as i64;
+    for dy in -e..h as i64 + e {
+        for dx in -e..w as i64 + e {
+            let sx = dx.clamp(0, w as i64 - 1) as u32;
+            let sy = dy.clamp(0, h as i64 - 1) as u32;
+            page.put_pixel(
+                (x as i64 + dx) as u32,
+                (y as i64 + dy) as u32,
+                *image.get_pixel(sx, sy),
+            );
+        }
+    }
+}
+
+// cells going across then down, partial cells at the right and bottom edge are skipped
+pub fn slice_grid(sheet: &RgbaImage, cell_width: u32, cell_height: u32) -> Vec<RgbaImage> {
+    let columns = sheet.width() / cell_width;
+    let rows = sheet.height() / cell_height;
+
+    (0..rows)
+        .flat_map(|r| (0..columns).map(move |c| (c, r)))
+        .map(|(c, r)| {
+            sheet
+                .view(c * cell_width, r * cell_height, cell_width, cell_height)
+                .to_image()
+        })
+        .collect()
+}
+
+// uv rects for a sprite sheet texture that is used as is, without packing it again
+pub struct SpriteSheet {
+    pub frames: Vec<UvRect>,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl SpriteSheet {
+    // margin is around the whole sheet, spacing is between cells
+    pub fn grid(
+        texture_width: u32,
+        texture_height: u32,
+        cell_width: u32,
+        cell_height: u32,
+        margin: u32,
+        spacing: u32,
+    ) -> Self {
+        let usable_w = texture_width.saturating_sub(margin * 2) + spacing;
+        let usable_h = texture_height.saturating_sub(margin * 2) + spacing;
+        let columns = usable_w / (cell_width + spacing);
+        let rows = usable_h / (cell_height + spacing);
+
+        let frames = (0..rows)
+            .flat_map(|r| (0..columns).map(move |c| (c, r)))
+            .map(|(c, r)| {
+                UvRect::from_pixels(
+                    margin + c * (cell_width + spacing),
+                    margin + r * (cell_height + spacing),
+                    cell_width,
+                    cell_height,
+                    texture_width,
+                    texture_height,
+                )
+            })
+            .collect();
+
+        Self {
+            frames,
+            columns,
+            rows,
+        }
+    }
+
+    pub fn frame(&self, column: u32, row: u32) -> Option<UvRect> {
+        if column >= self.columns {
+            return None;
+        }
+        self.frames
+            .get((row * self.columns + column) as usize)
+            .copied()
+    }
+}
+
+// the atlas pages uploaded as textures
+pub struct TextureAtlas {
+    pages: Vec<Texture>,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+impl TextureAtlas {
+    pub fn new(data: &AtlasData) -> Self {
+        Self {
+            pages: data.pages.iter().map(Texture::from_image).collect(),
+            regions: data.regions.clone(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(manifest: P) -> Result<Self, AtlasError> {
+        Ok(Self::new(&AtlasData::load(manifest)?))
+    }
+
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    // the page texture and the uv rect on it, ready for SpriteBatch::draw
+    pub fn get(&self, name: &str) -> Option<(&Texture, UvRect)> {
+        let region = self.regions.get(name)?;
+        Some((&self.pages[region.page], region.uv))
+    }
+
+    pub fn pages(&self) -> &[Texture] {
+        &self.pages
+    }
+}
+
+#[test]
+fn test_skyline_no_overlap() {
+    let mut packer = SkylinePacker::new(64, 64);
+    let sizes = [
+        (20, 10),
+        (10, 30),
+        (30, 20),
+        (5, 5),
+        (40, 8),
+        (12, 12),
+        (64, 4),
+    ];
+
+    let mut placed = Vec::new();
+    for (w, h) in sizes {
+        let r = packer.pack(w, h).expect("should fit");
+        assert!(r.x + w <= 64 && r.y + h <= 64);
+        placed.push((r.x, r.y, w, h));
+    }
+
+    for (i, a) in placed.iter().enumerate() {
+        for b in &placed[i + 1..] {
+            let apart =
+                a.0 + a.2 <= b.0 || b.0 + b.2 <= a.0 || a.1 + a.3 <= b.1 || b.1 + b.3 <= a.1;
+            assert!(apart, "{a:?} overlaps {b:?}");
+        }
+    }
+}
+
+#[test]
+fn test_skyline_full() {
+    let mut packer = SkylinePacker::new(16, 16);
+    for _ in 0..4 {
+        assert!(packer.pack(8, 8).is_some());
+    }
+    assert!(packer.pack(1, 1).is_none());
+}
+
+#[test]
+fn test_atlas_pages_and_extrude() {
+    use image::Rgba;
+
+    let mut builder = TextureAtlasBuilder::new(16, 16);
+    builder.set_padding(0);
+    builder.set_extrude(1);
+    builder.add_image("red", RgbaImage::from_pixel(6, 6, Rgba([255, 0, 0, 255])));
+    builder.add_image("blue", RgbaImage::from_pixel(6, 6, Rgba([0, 0, 255, 255])));
+    builder.add_image("big", RgbaImage::from_pixel(14, 14, Rgba([0, 255, 0, 255])));
+
+    let atlas = builder.build().unwrap();
+    assert_eq!(atlas.pages.len(), 2);
+
+    let red = atlas.regions["red"];
+    let page = &atlas.pages[red.page];
+    assert_eq!(page.get_pixel(red.x, red.y), &Rgba([255, 0, 0, 255]));
+    // the extruded border is a copy of the edge
+    assert_eq!(
+        page.get_pixel(red.x - 1, red.y - 1),
+        &Rgba([255, 0, 0, 255])
+    );
+    assert_eq!(red.uv, UvRect::from_pixels(red.x, red.y, 6, 6, 16, 16));
+
+    let mut builder = TextureAtlasBuilder::new(16, 16);
+    builder.add_image("huge", RgbaImage::new(17, 4));
+    assert!(matches!(builder.build(), Err(AtlasError::TooLarge { .. })));
+}
+
+#[test]
+fn test_sprite_sheet_grid() {
+    let sheet = SpriteSheet::grid(36, 18, 16, 16, 1, 2);
+    assert_eq!((sheet.columns, sheet.rows), (2, 1));
+    assert_eq!(
+        sheet.frame(1, 0),
+        Some(UvRect::from_pixels(19, 1, 16, 16, 36, 18))
+    );
+    assert_eq!(sheet.frame(2, 0), None);
+
+    let cells = slice_grid(&RgbaImage::new(40, 20), 16, 16);
+    assert_eq!(cells.len(), 2);
+}
+
+#[test]
+fn test_atlas_save_load() {
+    use image::Rgba;
+
+    let mut builder = TextureAtlasBuilder::new(32, 32);
+    builder.add_image("a b", RgbaImage::from_pixel(4, 4, Rgba([1, 2, 3, 255])));
+    builder.add_image("c", RgbaImage::from_pixel(8, 2, Rgba([4, 5, 6, 255])));
+    let atlas = builder.build().unwrap();
+
+    let dir = std::env::temp_dir().join(format!("qrgl-atlas-{}", std::process::id()));
+    let manifest = atlas.save(&dir, "test").unwrap();
+    let loaded = AtlasData::load(&manifest).unwrap();
+    std::fs::remove_dir_all(&dir).ok();
+
+    assert_eq!(loaded.regions, atlas.regions);
+    assert_eq!(loaded.pages, atlas.pages);
+}
@@ -0,0 +1,352 @@
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::rc::Rc;
+
+use bytemuck::Pod;
+
+use super::context::GlContext;
+
+// gpu buffers that know what they hold. the element type and where the buffer gets bound
+// are part of the type, so an index buffer can only be drawn with the index type it was
+// filled with and vertex data cant end up on the element binding
+
+// how often the contents of a buffer are going to change, the driver picks where to keep it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferUsage {
+    // set once and drawn a lot, meshes
+    Static,
+    // changed now and then and drawn a few times in between
+    Dynamic,
+    // rewritten every frame, ui, particles, debug lines
+    Stream,
+}
+
+impl BufferUsage {
+    pub fn gl_enum(&self) -> u32 {
+        match self {
+            BufferUsage::Static => gl::STATIC_DRAW,
+            BufferUsage::Dynamic => gl::DYNAMIC_DRAW,
+            BufferUsage::Stream => gl::STREAM_DRAW,
+        }
+    }
+}
+
+pub trait BufferTarget {
+    const TARGET: u32;
+}
+
+pub struct ArrayBuffer;
+impl BufferTarget for ArrayBuffer {
+    const TARGET: u32 = gl::ARRAY_BUFFER;
+}
+
+// the binding for this lives in the vao, bind the vao first
+pub struct ElementBuffer;
+impl BufferTarget for ElementBuffer {
+    const TARGET: u32 = gl::ELEMENT_ARRAY_BUFFER;
+}
+
+// the types glDrawElements can read indices as
+pub trait IndexType: Pod {
+    const GL_TYPE: u32;
+}
+
+impl IndexType for u8 {
+    const GL_TYPE: u32 = gl::UNSIGNED_BYTE;
+}
+
+impl IndexType for u16 {
+    const GL_TYPE: u32 = gl::UNSIGNED_SHORT;
+}
+
+impl IndexType for u32 {
+    const GL_TYPE: u32 = gl::UNSIGNED_INT;
+}
+
+pub type Vbo<T = f32> = Buffer<T, ArrayBuffer>;
+pub type Ebo<I = u32> = Buffer<I, ElementBuffer>;
+
+pub struct Buffer<T: Pod, K: BufferTarget> {
+    id: u32,
+    // elements written since the storage was made
+    len: usize,
+    // elements the gpu side has room for
+    capacity: usize,
+    usage: BufferUsage,
+    ctx: Rc<GlContext>,
+    marker: PhantomData<(T, K)>,
+}
+
+impl<T: Pod, K: BufferTarget> Buffer<T, K> {
+    pub fn new() -> Self {
+        Self::with_usage(BufferUsage::Static)
+    }
+
+    pub fn with_usage(usage: BufferUsage) -> Self {
+        let id = unsafe {
+            let mut id = 0;
+            gl::GenBuffers(1, &mut id);
+            id
+        };
+        Self {
+            id,
+            len: 0,
+            capacity: 0,
+            usage,
+            ctx: GlContext::current(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn new_bind() -> Self {
+        let b = Self::new();
+        b.bind();
+        b
+    }
+
+    pub fn new_bind_buffer(data: &[T]) -> Self {
+        let mut b = Self::new();
+        b.set_data(data);
+        b
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // same as len, from before buffers knew their type
+    pub fn get_size(&self) -> usize {
+        self.len
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn byte_size(&self) -> usize {
+        self.len * std::mem::size_of::<T>()
+    }
+
+    pub fn capacity_bytes(&self) -> usize {
+        self.capacity * std::mem::size_of::<T>()
+    }
+
+    pub fn target(&self) -> u32 {
+        K::TARGET
+    }
+
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+
+    // takes effect the next time the storage is made by set_data, reserve or orphan
+    pub fn set_usage(&mut self, usage: BufferUsage) {
+        self.usage = usage;
+    }
+
+    pub fn bind(&self) {
+        self.ctx.bind_buffer(K::TARGET, self.id);
+    }
+
+    // new storage that fits exactly the data
+    pub fn set_data(&mut self, data: &[T]) {
+        self.bind();
+        self.len = data.len();
+        self.capacity = data.len();
+        unsafe { self.buffer_data(data.as_ptr().cast()) }
+    }
+
+    // makes empty storage for at least len elements, whatever was in there is gone if it grew
+    pub fn reserve(&mut self, len: usize) {
+        if len > self.capacity {
+            self.bind();
+            self.len = 0;
+            self.capacity = len;
+            unsafe { self.buffer_data(std::ptr::null()) }
+        }
+    }
+
+    // overwrites part of the storage, offset counts elements from the start
+    pub fn set_sub_data(&mut self, offset: usize, data: &[T]) {
+        assert!(
+            offset + data.len() <= self.capacity,
+            "Buffer sub data of {} elements at {offset} does not fit in {}",
+            data.len(),
+            self.capacity
+        );
+
+        self.bind();
+        let size = std::mem::size_of::<T>();
+        unsafe {
+            gl::BufferSubData(
+                K::TARGET,
+                (offset * size) as isize,
+                std::mem::size_of_val(data) as isize,
+                data.as_ptr().cast(),
+            );
+        }
+        self.len = self.len.max(offset + data.len());
+    }
+
+    // swaps in fresh storage of the same size, draws still using the old one dont have
+    // to finish before we write again
+    pub fn orphan(&mut self) {
+        self.bind();
+        self.len = 0;
+        unsafe { self.buffer_data(std::ptr::null()) }
+    }
+
+    // copies the contents back from the gpu, slow, for debugging and tests
+    pub fn read_back(&self) -> Vec<T> {
+        self.read_back_range(0..self.len)
+    }
+
+    // any part of the storage, also what was written through a mapping
+    pub fn read_back_range(&self, range: Range<usize>) -> Vec<T> {
+        assert!(
+            range.start <= range.end && range.end <= self.capacity,
+            "Buffer read back of {range:?} is outside of {}",
+            self.capacity
+        );
+
+        let size = std::mem::size_of::<T>();
+        let mut data = vec![T::zeroed(); range.len()];
+        // the copy binding is free to use, the element one would change the bound vao
+        self.ctx.bind_buffer(gl::COPY_READ_BUFFER, self.id);
+        unsafe {
+            gl::GetBufferSubData(
+                gl::COPY_READ_BUFFER,
+                (range.start * size) as isize,
+                std::mem::size_of_val(data.as_slice()) as isize,
+                data.as_mut_ptr().cast(),
+            );
+        }
+        data
+    }
+
+    // copies on the gpu from another buffer, from counts elements in that one and to in this one
+    pub fn copy_from<O: BufferTarget>(
+        &mut self,
+        other: &Buffer<T, O>,
+        from: Range<usize>,
+        to: usize,
+    ) {
+        assert!(
+            from.start <= from.end
+                && from.end <= other.capacity
+                && to + from.len() <= self.capacity,
+            "Buffer copy of {from:?} to {to} does not fit in {} and {}",
+            other.capacity,
+            self.capacity
+        );
+
+        let size = std::mem::size_of::<T>();
+        self.ctx.bind_buffer(gl::COPY_READ_BUFFER, other.id);
+        self.ctx.bind_buffer(gl::COPY_WRITE_BUFFER, self.id);
+        unsafe {
+            gl::CopyBufferSubData(
+                gl::COPY_READ_BUFFER,
+                gl::COPY_WRITE_BUFFER,
+                (from.start * size) as isize,
+                (to * size) as isize,
+                (from.len() * size) as isize,
+            );
+        }
+        self.len = self.len.max(to + from.len());
+    }
+
+    // glBufferData for the whole capacity, data can be null to just make room
+    unsafe fn buffer_data(&self, data: *const std::ffi::c_void) {
+        gl::BufferData(
+            K::TARGET,
+            self.capacity_bytes() as isize,
+            data,
+            self.usage.gl_enum(),
+        );
+    }
+}
+
+impl<I: IndexType> Buffer<I, ElementBuffer> {
+    // draws count indices from first on with the bound vao and program, the ebo has to be
+    // the one the vao was set up with
+    pub fn draw_elements(&self, mode: u32, first: usize, count: usize) {
+        assert!(
+            first + count <= self.len,
+            "Drawing past the end of the indices"
+        );
+        unsafe {
+            gl::DrawElements(
+                mode,
+                count as i32,
+                I::GL_TYPE,
+                (first * std::mem::size_of::<I>()) as *const _,
+            );
+        }
+    }
+
+    pub fn draw_elements_instanced(&self, mode: u32, first: usize, count: usize, instances: usize) {
+        assert!(
+            first + count <= self.len,
+            "Drawing past the end of the indices"
+        );
+        unsafe {
+            gl::DrawElementsInstanced(
+                mode,
+                count as i32,
+                I::GL_TYPE,
+                (first * std::mem::size_of::<I>()) as *const _,
+                instances as i32,
+            );
+        }
+    }
+}
+
+impl<T: Pod, K: BufferTarget> Default for Buffer<T, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Pod, K: BufferTarget> Drop for Buffer<T, K> {
+    fn drop(&mut self) {
+        self.ctx.delete_buffer(self.id);
+    }
+}
+
+#[test]
+fn test_index_types() {
+    fn gl_type<I: IndexType>() -> (u32, usize) {
+        (I::GL_TYPE, std::mem::size_of::<I>())
+    }
+
+    assert_eq!(gl_type::<u8>(), (gl::UNSIGNED_BYTE, 1));
+    assert_eq!(gl_type::<u16>(), (gl::UNSIGNED_SHORT, 2));
+    assert_eq!(gl_type::<u32>(), (gl::UNSIGNED_INT, 4));
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn test_read_back() {
+    use crate::math::color::Color;
+
+    super::golden::render(1, 1, Color::BLACK, || {
+        let mut vbo = Vbo::<[f32; 2]>::with_usage(BufferUsage::Dynamic);
+        vbo.reserve(4);
+        assert_eq!((vbo.len(), vbo.capacity_bytes()), (0, 32));
+
+        vbo.set_sub_data(0, &[[1., 2.], [3., 4.]]);
+        vbo.set_sub_data(1, &[[5., 6.]]);
+        assert_eq!(vbo.len(), 2);
+        assert_eq!(vbo.byte_size(), 16);
+        assert_eq!(vbo.read_back(), vec![[1., 2.], [5., 6.]]);
+
+        let ebo = Ebo::<u16>::new_bind_buffer(&[0, 1, 2, 2, 3, 0]);
+        assert_eq!(ebo.target(), gl::ELEMENT_ARRAY_BUFFER);
+        assert_eq!(ebo.byte_size(), 12);
+        assert_eq!(ebo.read_back_range(2..4), vec![2, 2]);
+    });
+}
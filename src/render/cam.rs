@@ -1,3 +1,37 @@
-pub struct Camera {}
+use crate::math::vector::*;
 
-impl Camera {}
+use super::window::Resize;
+
+pub struct Camera {
+    viewport: Vec2i,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new(800, 600)
+    }
+}
+
+impl Camera {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            viewport: Vec2i::from([width, height]),
+        }
+    }
+
+    // hook this up with Window::on_resize so the camera follows the framebuffer
+    pub fn resize(&mut self, resize: &Resize) {
+        let (w, h) = resize.framebuffer_size;
+        if w > 0 && h > 0 {
+            self.viewport = Vec2i::from([w, h]);
+        }
+    }
+
+    pub fn viewport(&self) -> Vec2i {
+        self.viewport
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.viewport[X] as f32 / self.viewport[Y] as f32
+    }
+}
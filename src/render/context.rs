@@ -0,0 +1,479 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// what is bound in each gl context, so binding something that already is skips the gl call,
+// and deletes that wait for a point where nothing is in the middle of using them. gl state
+// belongs to the thread the context is current on, every gl object keeps a handle to the
+// context it was made in which also makes them !Send, so moving one to another thread wont
+// compile
+
+thread_local! {
+    // by the glfw window or egl context, 0 never gets in here, it means no context is current
+    static CONTEXTS: RefCell<HashMap<usize, Rc<GlContext>>> = RefCell::new(HashMap::new());
+}
+
+// a cached binding that is missing is unknown, the next bind always goes to gl
+#[derive(Debug, Default)]
+struct BindState {
+    program: Option<u32>,
+    vao: Option<u32>,
+    // by target
+    buffers: HashMap<u32, u32>,
+    active_unit: Option<u32>,
+    // by (unit, target)
+    textures: HashMap<(u32, u32), u32>,
+    draw_framebuffer: Option<u32>,
+    read_framebuffer: Option<u32>,
+}
+
+// every setter returns true when gl has to be told
+impl BindState {
+    fn use_program(&mut self, id: u32) -> bool {
+        self.program.replace(id) != Some(id)
+    }
+
+    fn bind_vao(&mut self, id: u32) -> bool {
+        let changed = self.vao.replace(id) != Some(id);
+        if changed {
+            // the element buffer binding is part of the vao
+            self.buffers.remove(&gl::ELEMENT_ARRAY_BUFFER);
+        }
+        changed
+    }
+
+    fn bind_buffer(&mut self, target: u32, id: u32) -> bool {
+        self.buffers.insert(target, id) != Some(id)
+    }
+
+    fn active_texture(&mut self, unit: u32) -> bool {
+        self.active_unit.replace(unit) != Some(unit)
+    }
+
+    fn bind_texture(&mut self, unit: u32, target: u32, id: u32) -> bool {
+        self.textures.insert((unit, target), id) != Some(id)
+    }
+
+    // gl::FRAMEBUFFER is the draw and the read one at once
+    fn bind_framebuffer(&mut self, target: u32, id: u32) -> bool {
+        let draw = target != gl::READ_FRAMEBUFFER && self.draw_framebuffer.replace(id) != Some(id);
+        let read = target != gl::DRAW_FRAMEBUFFER && self.read_framebuffer.replace(id) != Some(id);
+        draw || read
+    }
+
+    // a deleted name can come back from gl for something new, so whatever was bound to it
+    // is unknown now. other contexts in the group even still have the old object bound
+    fn forget(&mut self, garbage: &SharedGarbage) {
+        self.buffers.retain(|_, id| !garbage.buffers.contains(id));
+        self.textures.retain(|_, id| !garbage.textures.contains(id));
+        if matches!(self.program, Some(id) if garbage.programs.contains(&id)) {
+            self.program = None;
+        }
+    }
+
+    // gl unbinds a deleted vao, and only the context that made it can delete it
+    fn forget_vao(&mut self, id: u32) {
+        if self.vao == Some(id) {
+            self.bind_vao(0);
+        }
+    }
+
+    // same for framebuffers, the default one is bound in place of a deleted one
+    fn forget_framebuffer(&mut self, id: u32) {
+        for bound in [&mut self.draw_framebuffer, &mut self.read_framebuffer] {
+            if *bound == Some(id) {
+                *bound = Some(0);
+            }
+        }
+    }
+}
+
+// names any context in the share group can delete
+#[derive(Debug, Default)]
+struct SharedGarbage {
+    buffers: Vec<u32>,
+    programs: Vec<u32>,
+    textures: Vec<u32>,
+    renderbuffers: Vec<u32>,
+}
+
+impl SharedGarbage {
+    fn len(&self) -> usize {
+        self.buffers.len() + self.programs.len() + self.textures.len() + self.renderbuffers.len()
+    }
+}
+
+// vaos and framebuffers only exist in the context that made them
+#[derive(Debug, Default)]
+struct Garbage {
+    vaos: Vec<u32>,
+    framebuffers: Vec<u32>,
+}
+
+impl Garbage {
+    fn len(&self) -> usize {
+        self.vaos.len() + self.framebuffers.len()
+    }
+}
+
+#[derive(Debug)]
+pub struct GlContext {
+    key: usize,
+    // set once the window is gone, glfw can hand the same pointer to the next window
+    retired: Cell<bool>,
+    state: RefCell<BindState>,
+    garbage: RefCell<Garbage>,
+    // the same for every context in a share group
+    shared: Rc<RefCell<SharedGarbage>>,
+}
+
+impl GlContext {
+    fn new(key: usize, shared: Rc<RefCell<SharedGarbage>>) -> Self {
+        Self {
+            key,
+            retired: Cell::new(false),
+            state: RefCell::default(),
+            garbage: RefCell::default(),
+            shared,
+        }
+    }
+
+    // the one that is current on this thread, there has to be one. gl objects are made in
+    // it, without one they would end up in a context that does not exist
+    pub fn current() -> Rc<GlContext> {
+        let key = current_key();
+        assert!(key != 0, "No OpenGL context is current on this thread");
+        CONTEXTS.with(|contexts| {
+            contexts
+                .borrow_mut()
+                .entry(key)
+                .or_insert_with(|| Rc::new(GlContext::new(key, Rc::default())))
+                .clone()
+        })
+    }
+
+    // for switching contexts, the one being left gets its deletes done. does nothing when
+    // there is no context current yet
+    pub(crate) fn flush_current() {
+        let key = current_key();
+        let current = CONTEXTS.with(|contexts| contexts.borrow().get(&key).cloned());
+        if let Some(current) = current {
+            current.flush_deletes();
+        }
+    }
+
+    // Window calls this for every context it makes, share_with is any context already in
+    // the group the new one shares objects with
+    pub(crate) fn register(key: usize, share_with: Option<usize>) {
+        CONTEXTS.with(|contexts| {
+            let mut contexts = contexts.borrow_mut();
+            let shared = share_with
+                .and_then(|other| contexts.get(&other))
+                .map(|other| other.shared.clone())
+                .unwrap_or_default();
+            if let Some(old) = contexts.insert(key, Rc::new(GlContext::new(key, shared))) {
+                old.retired.set(true);
+            }
+        });
+    }
+
+    // the context is about to be destroyed along with its window. that can happen while the
+    // thread is exiting and the map is already gone, then there is nothing left to retire
+    pub(crate) fn retire(key: usize) {
+        let old = CONTEXTS.try_with(|contexts| contexts.borrow_mut().remove(&key));
+        if let Ok(Some(old)) = old {
+            old.retired.set(true);
+        }
+    }
+
+    pub fn is_current(&self) -> bool {
+        self.key != 0 && !self.retired.get() && self.key == current_key()
+    }
+
+    pub fn use_program(&self, id: u32) {
+        if self.state.borrow_mut().use_program(id) {
+            unsafe { gl::UseProgram(id) }
+        }
+    }
+
+    pub fn bind_vao(&self, id: u32) {
+        if self.state.borrow_mut().bind_vao(id) {
+            unsafe { gl::BindVertexArray(id) }
+        }
+    }
+
+    // element array buffers go into whatever vao is bound, bind the vao first
+    pub fn bind_buffer(&self, target: u32, id: u32) {
+        if self.state.borrow_mut().bind_buffer(target, id) {
+            unsafe { gl::BindBuffer(target, id) }
+        }
+    }
+
+    // unit counts from 0, not from gl::TEXTURE0
+    pub fn active_texture(&self, unit: u32) {
+        if self.state.borrow_mut().active_texture(unit) {
+            unsafe { gl::ActiveTexture(gl::TEXTURE0 + unit) }
+        }
+    }
+
+    // on the active unit, which is 0 if nothing picked one yet
+    pub fn bind_texture(&self, target: u32, id: u32) {
+        let unit = self.state.borrow().active_unit;
+        let unit = unit.unwrap_or_else(|| {
+            self.active_texture(0);
+            0
+        });
+        if self.state.borrow_mut().bind_texture(unit, target, id) {
+            unsafe { gl::BindTexture(target, id) }
+        }
+    }
+
+    // draw and read framebuffers, a RenderTarget's or 0 for the window
+    pub fn bind_framebuffer(&self, target: u32, id: u32) {
+        if self.state.borrow_mut().bind_framebuffer(target, id) {
+            unsafe { gl::BindFramebuffer(target, id) }
+        }
+    }
+
+    // forgets every cached binding, call it after binding things with raw gl calls
+    pub fn invalidate(&self) {
+        *self.state.borrow_mut() = BindState::default();
+    }
+
+    pub(crate) fn delete_buffer(&self, id: u32) {
+        self.shared.borrow_mut().buffers.push(id);
+    }
+
+    pub(crate) fn delete_vao(&self, id: u32) {
+        self.garbage.borrow_mut().vaos.push(id);
+    }
+
+    pub(crate) fn delete_program(&self, id: u32) {
+        self.shared.borrow_mut().programs.push(id);
+    }
+
+    pub(crate) fn delete_texture(&self, id: u32) {
+        self.shared.borrow_mut().textures.push(id);
+    }
+
+    pub(crate) fn delete_framebuffer(&self, id: u32) {
+        self.garbage.borrow_mut().framebuffers.push(id);
+    }
+
+    pub(crate) fn delete_renderbuffer(&self, id: u32) {
+        self.shared.borrow_mut().renderbuffers.push(id);
+    }
+
+    // waiting for this context, shared ones included
+    pub fn pending_deletes(&self) -> usize {
+        self.garbage.borrow().len() + self.shared.borrow().len()
+    }
+
+    // actually deletes everything dropped since the last call. Window::swap_buffers does
+    // this once a frame and make_current does it for the context it leaves, dropping a
+    // RenderTarget or a Window does too. it only happens while this context is current,
+    // until then everything waits for the next call
+    pub fn flush_deletes(&self) {
+        if !gl::DeleteBuffers::is_loaded() || self.pending_deletes() == 0 || !self.is_current() {
+            return;
+        }
+
+        let garbage = std::mem::take(&mut *self.garbage.borrow_mut());
+        let shared = std::mem::take(&mut *self.shared.borrow_mut());
+
+        CONTEXTS.with(|contexts| {
+            for other in contexts.borrow().values() {
+                if !std::ptr::eq(&**other, self) && Rc::ptr_eq(&other.shared, &self.shared) {
+                    other.state.borrow_mut().forget(&shared);
+                }
+            }
+        });
+
+        let mut state = self.state.borrow_mut();
+        unsafe {
+            // a program stays in use after it is deleted until something else is, so move
+            // off of it or gl could hand the name out again while it is still in use here
+            if matches!(state.program, Some(id) if shared.programs.contains(&id)) {
+                gl::UseProgram(0);
+            }
+            state.forget(&shared);
+            for id in &garbage.vaos {
+                state.forget_vao(*id);
+            }
+            for id in &garbage.framebuffers {
+                state.forget_framebuffer(*id);
+            }
+
+            delete(&shared.buffers, gl::DeleteBuffers);
+            delete(&garbage.vaos, gl::DeleteVertexArrays);
+            delete(&shared.textures, gl::DeleteTextures);
+            delete(&garbage.framebuffers, gl::DeleteFramebuffers);
+            delete(&shared.renderbuffers, gl::DeleteRenderbuffers);
+            for id in &shared.programs {
+                gl::DeleteProgram(*id);
+            }
+        }
+    }
+}
+
+// how blending was set up before a draw switched on alpha blending, restore puts it back
+// so whatever gets drawn next doesnt suddenly blend
+pub(crate) struct SavedBlend {
+    enabled: bool,
+    // src rgb, dst rgb, src alpha, dst alpha
+    func: [i32; 4],
+}
+
+impl SavedBlend {
+    // remembers the current blending and switches to plain alpha blending
+    pub(crate) fn alpha() -> Self {
+        let mut func = [0; 4];
+        let names = [
+            gl::BLEND_SRC_RGB,
+            gl::BLEND_DST_RGB,
+            gl::BLEND_SRC_ALPHA,
+            gl::BLEND_DST_ALPHA,
+        ];
+        unsafe {
+            for (name, value) in names.into_iter().zip(&mut func) {
+                gl::GetIntegerv(name, value);
+            }
+            let enabled = gl::IsEnabled(gl::BLEND) == gl::TRUE;
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            Self { enabled, func }
+        }
+    }
+
+    pub(crate) fn restore(self) {
+        let [src_rgb, dst_rgb, src_alpha, dst_alpha] = self.func.map(|f| f as u32);
+        unsafe {
+            gl::BlendFuncSeparate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+            if !self.enabled {
+                gl::Disable(gl::BLEND);
+            }
+        }
+    }
+}
+
+// glfw windows are known by their window, a headless context by its egl context
+fn current_key() -> usize {
+    let key = unsafe { glfw::ffi::glfwGetCurrentContext() as usize };
+    #[cfg(feature = "egl")]
+    if key == 0 {
+        return super::headless::current_context();
+    }
+    key
+}
+
+unsafe fn delete(ids: &[u32], f: unsafe fn(i32, *const u32)) {
+    if !ids.is_empty() {
+        f(ids.len() as i32, ids.as_ptr());
+    }
+}
+
+#[test]
+fn test_bind_cache() {
+    let mut state = BindState::default();
+
+    // unknown until something is bound, then only changes go through
+    assert!(state.use_program(3));
+    assert!(!state.use_program(3));
+    assert!(state.bind_buffer(gl::ARRAY_BUFFER, 5));
+    assert!(!state.bind_buffer(gl::ARRAY_BUFFER, 5));
+
+    // another vao brings its own element buffer
+    assert!(state.bind_vao(1));
+    assert!(state.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, 6));
+    assert!(!state.bind_vao(1));
+    assert!(!state.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, 6));
+    assert!(state.bind_vao(2));
+    assert!(state.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, 6));
+    assert!(!state.bind_buffer(gl::ARRAY_BUFFER, 5));
+
+    // textures are per unit
+    state.active_texture(0);
+    assert!(state.bind_texture(0, gl::TEXTURE_2D, 7));
+    assert!(state.bind_texture(1, gl::TEXTURE_2D, 7));
+    assert!(!state.bind_texture(0, gl::TEXTURE_2D, 7));
+
+    // a deleted name can come back from gl for something new, that has to bind again
+    state.forget(&SharedGarbage {
+        buffers: vec![5],
+        programs: vec![3],
+        textures: vec![7],
+        ..Default::default()
+    });
+    state.forget_vao(2);
+    assert!(state.bind_buffer(gl::ARRAY_BUFFER, 5));
+    assert!(state.bind_texture(1, gl::TEXTURE_2D, 7));
+    assert!(state.use_program(3));
+    assert_eq!(state.vao, Some(0));
+    assert!(!state.buffers.contains_key(&gl::ELEMENT_ARRAY_BUFFER));
+
+    // binding both framebuffers covers the draw and the read one
+    assert!(state.bind_framebuffer(gl::FRAMEBUFFER, 4));
+    assert!(!state.bind_framebuffer(gl::READ_FRAMEBUFFER, 4));
+    assert!(state.bind_framebuffer(gl::DRAW_FRAMEBUFFER, 8));
+    assert!(state.bind_framebuffer(gl::FRAMEBUFFER, 4));
+    state.forget_framebuffer(4);
+    assert!(!state.bind_framebuffer(gl::FRAMEBUFFER, 0));
+}
+
+#[test]
+fn test_deferred_delete() {
+    let first = GlContext::new(1, Rc::default());
+    let second = GlContext::new(2, first.shared.clone());
+    let other_group = GlContext::new(3, Rc::default());
+
+    first.delete_buffer(1);
+    first.delete_texture(2);
+    first.delete_vao(3);
+    first.delete_program(4);
+    first.delete_framebuffer(5);
+    assert_eq!(first.pending_deletes(), 5);
+
+    // the group can delete shared objects from any of its contexts, but a vao or a
+    // framebuffer has to wait for the one that made it
+    assert_eq!(second.pending_deletes(), 3);
+    assert_eq!(other_group.pending_deletes(), 0);
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn test_saved_blend() {
+    super::golden::render(4, 4, crate::math::color::Color::BLACK, || unsafe {
+        let func = |name| {
+            let mut value = 0;
+            gl::GetIntegerv(name, &mut value);
+            value as u32
+        };
+        gl::Disable(gl::BLEND);
+        gl::BlendFunc(gl::ONE, gl::ZERO);
+
+        let saved = SavedBlend::alpha();
+        assert_eq!(gl::IsEnabled(gl::BLEND), gl::TRUE);
+        assert_eq!(func(gl::BLEND_SRC_RGB), gl::SRC_ALPHA);
+
+        saved.restore();
+        assert_eq!(gl::IsEnabled(gl::BLEND), gl::FALSE);
+        assert_eq!(func(gl::BLEND_SRC_RGB), gl::ONE);
+        assert_eq!(func(gl::BLEND_DST_ALPHA), gl::ZERO);
+    });
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn test_render_target_deletes() {
+    use super::framebuffer::RenderTarget;
+
+    super::golden::render(4, 4, crate::math::color::Color::BLACK, || {
+        let ctx = GlContext::current();
+        drop(RenderTarget::new(8, 8));
+        // the color texture went with the rest and didnt wait for the next flush
+        assert_eq!(ctx.pending_deletes(), 0);
+    });
+
+    // with nothing current there is no context to hand out
+    assert!(std::panic::catch_unwind(GlContext::current).is_err());
+}
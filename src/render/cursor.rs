@@ -0,0 +1,88 @@
+use glfw::{PixelImage, StandardCursor};
+use image::RgbaImage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorMode {
+    Normal,
+    // invisible over the window, but moves and leaves the window like normal
+    Hidden,
+    // hidden and kept inside the window, positions keep going past the window edges
+    Disabled,
+    // disabled plus raw (unaccelerated) mouse motion when the platform supports it, for fps cameras
+    Locked,
+}
+
+impl From<CursorMode> for glfw::CursorMode {
+    fn from(mode: CursorMode) -> Self {
+        match mode {
+            CursorMode::Normal => glfw::CursorMode::Normal,
+            CursorMode::Hidden => glfw::CursorMode::Hidden,
+            CursorMode::Disabled | CursorMode::Locked => glfw::CursorMode::Disabled,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorShape {
+    Arrow,
+    IBeam,
+    Crosshair,
+    Hand,
+    HResize,
+    VResize,
+}
+
+impl From<CursorShape> for StandardCursor {
+    fn from(shape: CursorShape) -> Self {
+        match shape {
+            CursorShape::Arrow => StandardCursor::Arrow,
+            CursorShape::IBeam => StandardCursor::IBeam,
+            CursorShape::Crosshair => StandardCursor::Crosshair,
+            CursorShape::Hand => StandardCursor::Hand,
+            CursorShape::HResize => StandardCursor::HResize,
+            CursorShape::VResize => StandardCursor::VResize,
+        }
+    }
+}
+
+pub struct Cursor {
+    pub(crate) handle: glfw::Cursor,
+}
+
+impl Cursor {
+    pub fn standard(shape: CursorShape) -> Self {
+        Self {
+            handle: glfw::Cursor::standard(shape.into()),
+        }
+    }
+
+    // hot spot is in pixels from the top left of the image
+    pub fn from_image(image: &RgbaImage, hot_x: u32, hot_y: u32) -> Self {
+        let (width, height) = image.dimensions();
+
+        // glfw wants each pixel packed into a u32 with the bytes laid out as r, g, b, a in memory
+        let pixels = image
+            .pixels()
+            .map(|p| u32::from_ne_bytes(p.0))
+            .collect::<Vec<u32>>();
+
+        Self {
+            handle: glfw::Cursor::create_from_pixels(
+                PixelImage {
+                    width,
+                    height,
+                    pixels,
+                },
+                hot_x,
+                hot_y,
+            ),
+        }
+    }
+
+    pub fn from_file(path: &str, hot_x: u32, hot_y: u32) -> Self {
+        let image = image::open(path)
+            .expect("Could not open the cursor image")
+            .into_rgba8();
+        Self::from_image(&image, hot_x, hot_y)
+    }
+}
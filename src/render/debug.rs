@@ -1 +1,473 @@
+use std::cell::RefCell;
+use std::f32::consts::TAU;
 
+use crate::math::color::Color;
+use crate::math::vector::*;
+
+use super::context::SavedBlend;
+use super::ogl::{Material, Vao, VertexPosInfo3D, VertexRgbaInfo, VertexUvInfo};
+use super::ring::RingBuffer;
+use super::shapes::Circle;
+use super::sprite::sprite_material;
+use super::text::{Align, Font};
+
+// immediate mode debug drawing, call any of the shape functions from anywhere during the frame
+// and draw once at the end of it, everything goes out in a single draw call over the scene.
+// positions are in pixels from the top left of the screen like sprites
+
+const VERTEX_FLOATS: usize = 9;
+const TEXT_SIZE: f32 = 13.;
+
+thread_local! {
+    static DEBUG: RefCell<DebugDraw> = RefCell::new(DebugDraw::new());
+}
+
+#[derive(Debug, Clone)]
+enum Shape {
+    Line(Vec2, Vec2),
+    Rect(Vec2, Vec2),
+    Circle(Vec2, f32),
+    Arrow(Vec2, Vec2),
+    Cross(Vec2, f32),
+    Grid {
+        origin: Vec2,
+        cell: Vec2,
+        columns: u32,
+        rows: u32,
+    },
+    Text(Vec2, String),
+}
+
+struct Command {
+    // handles find their command by this, it never comes back after the command expires
+    id: u64,
+    shape: Shape,
+    color: Color,
+    thickness: f32,
+    // seconds left after this frame, everything is drawn at least once
+    remaining: f32,
+}
+
+struct Renderer {
+    vao: Vao,
+    ring: RingBuffer,
+    material: Material,
+}
+
+impl Renderer {
+    fn new() -> Self {
+        let mut vao = Vao::new_bind();
+        let ring = RingBuffer::new(64 * 1024);
+
+        vao.add_attribute(ring.vbo(), &VertexPosInfo3D);
+        vao.add_attribute(ring.vbo(), &VertexUvInfo);
+        vao.add_attribute(ring.vbo(), &VertexRgbaInfo);
+
+        Self {
+            vao,
+            ring,
+            material: sprite_material(),
+        }
+    }
+}
+
+struct DebugDraw {
+    // in the order they were pushed, so sorted by id
+    commands: Vec<Command>,
+    next_id: u64,
+    enabled: bool,
+    // glyphs and a white texel share the font's first page so it is all one texture
+    font: Font,
+    vertices: Vec<f32>,
+    renderer: Option<Renderer>,
+}
+
+// changes the shape it was made for, does nothing once that shape has expired
+#[derive(Debug, Clone, Copy)]
+pub struct DebugHandle(Option<u64>);
+
+impl DebugHandle {
+    // keep drawing it every frame for this many seconds
+    pub fn lasting(self, seconds: f32) -> Self {
+        self.modify(|c| c.remaining = seconds);
+        self
+    }
+
+    // line width in pixels, 1 by default
+    pub fn thickness(self, pixels: f32) -> Self {
+        self.modify(|c| c.thickness = pixels);
+        self
+    }
+
+    fn modify(&self, f: impl FnOnce(&mut Command)) {
+        if let Some(id) = self.0 {
+            DEBUG.with(|d| {
+                let commands = &mut d.borrow_mut().commands;
+                if let Ok(index) = commands.binary_search_by_key(&id, |c| c.id) {
+                    f(&mut commands[index]);
+                }
+            });
+        }
+    }
+}
+
+fn push(shape: Shape, color: Color) -> DebugHandle {
+    DEBUG.with(|d| DebugHandle(d.borrow_mut().push(shape, color)))
+}
+
+pub fn line(from: Vec2, to: Vec2, color: Color) -> DebugHandle {
+    push(Shape::Line(from, to), color)
+}
+
+// an outline, position is the top left corner
+pub fn rect(position: Vec2, size: Vec2, color: Color) -> DebugHandle {
+    push(Shape::Rect(position, size), color)
+}
+
+pub fn circle(center: Vec2, radius: f32, color: Color) -> DebugHandle {
+    push(Shape::Circle(center, radius), color)
+}
+
+// a line with a head on the to end
+pub fn arrow(from: Vec2, to: Vec2, color: Color) -> DebugHandle {
+    push(Shape::Arrow(from, to), color)
+}
+
+// a + marker, size is the full width of it
+pub fn cross(center: Vec2, size: f32, color: Color) -> DebugHandle {
+    push(Shape::Cross(center, size), color)
+}
+
+pub fn grid(origin: Vec2, cell: Vec2, columns: u32, rows: u32, color: Color) -> DebugHandle {
+    push(
+        Shape::Grid {
+            origin,
+            cell,
+            columns,
+            rows,
+        },
+        color,
+    )
+}
+
+// in the built in debug font, position is the top left of the first line
+pub fn text(position: Vec2, string: &str, color: Color) -> DebugHandle {
+    push(Shape::Text(position, string.to_string()), color)
+}
+
+// when disabled nothing is queued or drawn, shapes already lasting are kept for later
+pub fn set_enabled(enabled: bool) {
+    DEBUG.with(|d| d.borrow_mut().enabled = enabled);
+}
+
+pub fn clear() {
+    DEBUG.with(|d| d.borrow_mut().commands.clear());
+}
+
+// draws everything queued on top of whatever is bound and counts lifetimes down by dt
+pub fn draw(screen_width: f32, screen_height: f32, dt: f32) {
+    DEBUG.with(|d| d.borrow_mut().draw(vec2(screen_width, screen_height), dt));
+}
+
+impl DebugDraw {
+    fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+            next_id: 0,
+            enabled: true,
+            font: Font::debug(),
+            vertices: Vec::new(),
+            renderer: None,
+        }
+    }
+
+    fn push(&mut self, shape: Shape, color: Color) -> Option<u64> {
+        if !self.enabled {
+            return None;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.commands.push(Command {
+            id,
+            shape,
+            color,
+            thickness: 1.,
+            remaining: 0.,
+        });
+        Some(id)
+    }
+
+    fn tessellate(&mut self) {
+        self.vertices.clear();
+
+        let white = self.font.white_texel().uv;
+        let white = (white.u0, white.v0);
+
+        let mut out = Tessellator {
+            vertices: &mut self.vertices,
+            color: [0.; 4],
+            thickness: 1.,
+            white,
+        };
+
+        for command in &self.commands {
+            out.color = command.color.to_rgba_f32();
+            out.thickness = command.thickness;
+
+            match &command.shape {
+                Shape::Line(a, b) => out.segment(*a, *b),
+                Shape::Rect(p, s) => {
+                    let (x0, y0, x1, y1) = (p[X], p[Y], p[X] + s[X], p[Y] + s[Y]);
+                    out.segment(vec2(x0, y0), vec2(x1, y0));
+                    out.segment(vec2(x1, y0), vec2(x1, y1));
+                    out.segment(vec2(x1, y1), vec2(x0, y1));
+                    out.segment(vec2(x0, y1), vec2(x0, y0));
+                }
+                Shape::Circle(c, r) => {
+                    let segments = Circle::segments_for(*r);
+                    let point = |i: usize| {
+                        let (sin, cos) = (i as f32 / segments as f32 * TAU).sin_cos();
+                        vec2(c[X] + cos * r, c[Y] + sin * r)
+                    };
+                    for i in 0..segments {
+                        out.segment(point(i), point(i + 1));
+                    }
+                }
+                Shape::Arrow(a, b) => {
+                    out.segment(*a, *b);
+
+                    let (dx, dy) = (b[X] - a[X], b[Y] - a[Y]);
+                    let length = (dx * dx + dy * dy).sqrt();
+                    if length > 0. {
+                        let head = (length * 0.25).min(12. + command.thickness * 2.);
+                        let (ux, uy) = (dx / length * head, dy / length * head);
+                        // the two sides of the head are the direction turned back 30 degrees either way
+                        let (sin, cos) = (TAU / 12.).sin_cos();
+                        for side in [-1., 1.] {
+                            let hx = ux * cos - uy * sin * side;
+                            let hy = ux * sin * side + uy * cos;
+                            out.segment(*b, vec2(b[X] - hx, b[Y] - hy));
+                        }
+                    }
+                }
+                Shape::Cross(c, size) => {
+                    let h = size / 2.;
+                    out.segment(vec2(c[X] - h, c[Y]), vec2(c[X] + h, c[Y]));
+                    out.segment(vec2(c[X], c[Y] - h), vec2(c[X], c[Y] + h));
+                }
+                Shape::Grid {
+                    origin,
+                    cell,
+                    columns,
+                    rows,
+                } => {
+                    let (w, h) = (cell[X] * *columns as f32, cell[Y] * *rows as f32);
+                    for i in 0..=*columns {
+                        let x = origin[X] + cell[X] * i as f32;
+                        out.segment(vec2(x, origin[Y]), vec2(x, origin[Y] + h));
+                    }
+                    for i in 0..=*rows {
+                        let y = origin[Y] + cell[Y] * i as f32;
+                        out.segment(vec2(origin[X], y), vec2(origin[X] + w, y));
+                    }
+                }
+                Shape::Text(position, string) => {
+                    let (glyphs, _) = self.font.layout(string, TEXT_SIZE, None, 1., Align::Left);
+                    for p in glyphs {
+                        let (glyph, scale) = self.font.glyph(p.c, TEXT_SIZE);
+                        let x = (position[X] + p.x + glyph.offset.0 * scale).round();
+                        let y = (position[Y] + p.y + glyph.offset.1 * scale).round();
+                        let (w, h) = (glyph.width as f32 * scale, glyph.height as f32 * scale);
+                        let uv = glyph.uv;
+
+                        out.quad(
+                            [(x, y), (x + w, y), (x + w, y + h), (x, y + h)],
+                            [
+                                (uv.u0, uv.v0),
+                                (uv.u1, uv.v0),
+                                (uv.u1, uv.v1),
+                                (uv.u0, uv.v1),
+                            ],
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn expire(&mut self, dt: f32) {
+        for command in &mut self.commands {
+            command.remaining -= dt;
+        }
+        self.commands.retain(|c| c.remaining > 0.);
+    }
+
+    fn draw(&mut self, screen_size: Vec2, dt: f32) {
+        // lasting shapes keep their time left while disabled
+        if !self.enabled {
+            return;
+        }
+
+        if !self.commands.is_empty() {
+            self.tessellate();
+            self.font.upload();
+
+            let renderer = self.renderer.get_or_insert_with(Renderer::new);
+            renderer
+                .material
+                .shader
+                .set_uniform("screen_size", screen_size);
+            renderer.material.bind();
+            renderer.vao.bind();
+            renderer.ring.begin_frame();
+            let first = renderer
+                .ring
+                .write(&self.vertices, VERTEX_FLOATS * std::mem::size_of::<f32>());
+
+            let blend = SavedBlend::alpha();
+            unsafe {
+                let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+                gl::Disable(gl::DEPTH_TEST);
+
+                self.font.page_texture(0).unwrap().bind(0);
+                gl::DrawArrays(
+                    gl::TRIANGLES,
+                    first as i32,
+                    (self.vertices.len() / VERTEX_FLOATS) as i32,
+                );
+
+                if depth_test {
+                    gl::Enable(gl::DEPTH_TEST);
+                }
+            }
+            blend.restore();
+            renderer.ring.end_frame();
+        }
+
+        self.expire(dt);
+    }
+}
+
+struct Tessellator<'a> {
+    vertices: &'a mut Vec<f32>,
+    color: [f32; 4],
+    thickness: f32,
+    white: (f32, f32),
+}
+
+impl Tessellator<'_> {
+    // corners clockwise from the top left, as two triangles
+    fn quad(&mut self, corners: [(f32, f32); 4], uvs: [(f32, f32); 4]) {
+        let [r, g, b, a] = self.color;
+        for i in [0, 1, 2, 2, 3, 0] {
+            let ((x, y), (u, v)) = (corners[i], uvs[i]);
+            self.vertices
+                .extend_from_slice(&[x, y, 0., u, v, r, g, b, a]);
+        }
+    }
+
+    // a line is a thin quad around it, thickness wide
+    fn segment(&mut self, a: Vec2, b: Vec2) {
+        let (dx, dy) = (b[X] - a[X], b[Y] - a[Y]);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length == 0. {
+            return;
+        }
+
+        let (nx, ny) = (
+            -dy / length * self.thickness / 2.,
+            dx / length * self.thickness / 2.,
+        );
+        self.quad(
+            [
+                (a[X] + nx, a[Y] + ny),
+                (b[X] + nx, b[Y] + ny),
+                (b[X] - nx, b[Y] - ny),
+                (a[X] - nx, a[Y] - ny),
+            ],
+            [self.white; 4],
+        );
+    }
+}
+
+#[test]
+fn test_tessellate() {
+    let mut debug = DebugDraw::new();
+    let vertex_count = |debug: &mut DebugDraw| {
+        debug.tessellate();
+        debug.vertices.len() / VERTEX_FLOATS
+    };
+
+    debug.push(Shape::Line(vec2(0., 0.), vec2(10., 0.)), Color::RED);
+    assert_eq!(vertex_count(&mut debug), 6);
+
+    // the line is 1 pixel thick around y = 0
+    let ys: Vec<f32> = debug.vertices.chunks(VERTEX_FLOATS).map(|v| v[1]).collect();
+    assert_eq!(ys, vec![0.5, 0.5, -0.5, -0.5, -0.5, 0.5]);
+
+    debug.commands.clear();
+    debug.push(Shape::Rect(vec2(0., 0.), vec2(5., 5.)), Color::RED);
+    debug.push(
+        Shape::Grid {
+            origin: vec2(0., 0.),
+            cell: vec2(10., 10.),
+            columns: 2,
+            rows: 3,
+        },
+        Color::RED,
+    );
+    assert_eq!(vertex_count(&mut debug), (4 + 3 + 4) * 6);
+
+    debug.commands.clear();
+    debug.push(Shape::Text(vec2(0., 0.), "a b".to_string()), Color::WHITE);
+    // the space has no pixels but still gets a quad, every glyph is one
+    assert_eq!(vertex_count(&mut debug), 18);
+}
+
+#[test]
+fn test_lifetimes() {
+    let mut debug = DebugDraw::new();
+
+    debug.push(Shape::Cross(vec2(0., 0.), 4.), Color::RED);
+    let lasting = debug.push(Shape::Circle(vec2(0., 0.), 4.), Color::RED);
+    debug.commands[1].remaining = 0.5;
+    assert_eq!(lasting, Some(1));
+
+    debug.expire(0.3);
+    assert_eq!(debug.commands.len(), 1);
+    debug.expire(0.3);
+    assert!(debug.commands.is_empty());
+
+    debug.enabled = false;
+    assert_eq!(debug.push(Shape::Cross(vec2(0., 0.), 4.), Color::RED), None);
+}
+
+#[test]
+fn test_stale_handle() {
+    clear();
+    let gone = cross(vec2(0., 0.), 4., Color::RED);
+    let kept = circle(vec2(0., 0.), 4., Color::RED).lasting(1.);
+    DEBUG.with(|d| d.borrow_mut().expire(0.5));
+
+    // the cross expired, its handle must not reach the circle that moved into its place
+    let fresh = line(vec2(0., 0.), vec2(1., 0.), Color::RED);
+    gone.thickness(5.);
+    kept.thickness(2.);
+    fresh.thickness(3.);
+    let thickness = DEBUG.with(|d| {
+        d.borrow()
+            .commands
+            .iter()
+            .map(|c| c.thickness)
+            .collect::<Vec<_>>()
+    });
+    assert_eq!(thickness, vec![2., 3.]);
+
+    // nothing counts down while disabled
+    set_enabled(false);
+    draw(100., 100., 10.);
+    set_enabled(true);
+    assert_eq!(DEBUG.with(|d| d.borrow().commands.len()), 2);
+    clear();
+}
@@ -0,0 +1,466 @@
+use std::cell::Cell;
+use std::fmt::Display;
+use std::rc::Rc;
+
+use image::RgbaImage;
+
+use super::context::GlContext;
+use super::readback::{self, PixelSource};
+use super::texture::Texture;
+use super::window::Resize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FramebufferError {
+    ZeroSize,
+    NoColorAttachments,
+    TooManyColorAttachments { requested: usize, max: usize },
+    TooManySamples { requested: u32, max: u32 },
+    Incomplete { status: u32, reason: &'static str },
+}
+
+impl Display for FramebufferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FramebufferError::ZeroSize => write!(f, "Render target has a width or height of 0"),
+            FramebufferError::NoColorAttachments => {
+                write!(f, "Render target needs at least one color attachment")
+            }
+            FramebufferError::TooManyColorAttachments { requested, max } => write!(
+                f,
+                "Render target asked for {requested} color attachments but the driver only supports {max}"
+            ),
+            FramebufferError::TooManySamples { requested, max } => write!(
+                f,
+                "Render target asked for {requested} msaa samples but the driver only supports {max}"
+            ),
+            FramebufferError::Incomplete { status, reason } => {
+                write!(f, "Framebuffer is not complete (0x{status:X}): {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FramebufferError {}
+
+fn check_status() -> Result<(), FramebufferError> {
+    let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+
+    let reason = match status {
+        gl::FRAMEBUFFER_COMPLETE => return Ok(()),
+        gl::FRAMEBUFFER_UNDEFINED => "the default framebuffer does not exist",
+        gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => {
+            "an attachment is missing storage or has a zero size"
+        }
+        gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "no images are attached",
+        gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "a draw buffer points at a missing attachment",
+        gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "the read buffer points at a missing attachment",
+        gl::FRAMEBUFFER_UNSUPPORTED => "the driver does not support this combination of formats",
+        gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "attachments have different sample counts",
+        gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "attachments are not all layered the same way",
+        _ => "unknown framebuffer status",
+    };
+
+    Err(FramebufferError::Incomplete { status, reason })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthStencil {
+    None,
+    Depth,
+    DepthStencil,
+}
+
+impl DepthStencil {
+    fn format(&self) -> Option<(u32, u32)> {
+        match self {
+            DepthStencil::None => None,
+            DepthStencil::Depth => Some((gl::DEPTH_COMPONENT24, gl::DEPTH_ATTACHMENT)),
+            DepthStencil::DepthStencil => {
+                Some((gl::DEPTH24_STENCIL8, gl::DEPTH_STENCIL_ATTACHMENT))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderTargetBuilder {
+    width: u32,
+    height: u32,
+    color_attachments: usize,
+    depth_stencil: DepthStencil,
+    samples: u32,
+}
+
+impl RenderTargetBuilder {
+    // starts with no color attachments, no depth and no msaa
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            color_attachments: 0,
+            depth_stencil: DepthStencil::None,
+            samples: 1,
+        }
+    }
+
+    // each one is an rgba8 texture, written to by layout(location = n) outputs in order
+    pub fn add_color_attachment(&mut self) {
+        self.color_attachments += 1;
+    }
+
+    pub fn set_depth_stencil(&mut self, depth_stencil: DepthStencil) {
+        self.depth_stencil = depth_stencil;
+    }
+
+    // anything over 1 draws into multisampled renderbuffers that get resolved into the textures
+    pub fn set_samples(&mut self, samples: u32) {
+        self.samples = samples.max(1);
+    }
+
+    pub fn build(self) -> Result<RenderTarget, FramebufferError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(FramebufferError::ZeroSize);
+        }
+
+        if self.color_attachments == 0 {
+            return Err(FramebufferError::NoColorAttachments);
+        }
+
+        let (max_attachments, max_samples) = unsafe {
+            let mut attachments = 0;
+            let mut samples = 0;
+            gl::GetIntegerv(gl::MAX_COLOR_ATTACHMENTS, &mut attachments);
+            gl::GetIntegerv(gl::MAX_SAMPLES, &mut samples);
+            (attachments as usize, samples as u32)
+        };
+
+        if self.color_attachments > max_attachments {
+            return Err(FramebufferError::TooManyColorAttachments {
+                requested: self.color_attachments,
+                max: max_attachments,
+            });
+        }
+
+        if self.samples > max_samples.max(1) {
+            return Err(FramebufferError::TooManySamples {
+                requested: self.samples,
+                max: max_samples,
+            });
+        }
+
+        let multisampled = self.samples > 1;
+
+        let colors = (0..self.color_attachments)
+            .map(|_| Texture::empty(self.width, self.height))
+            .collect::<Vec<_>>();
+
+        let ctx = GlContext::current();
+        let mut fbo = 0;
+        let mut depth = None;
+        let mut msaa = None;
+
+        unsafe {
+            let mut prev = 0;
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut prev);
+
+            gl::GenFramebuffers(1, &mut fbo);
+            ctx.bind_framebuffer(gl::FRAMEBUFFER, fbo);
+
+            for (i, color) in colors.iter().enumerate() {
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0 + i as u32,
+                    gl::TEXTURE_2D,
+                    color.id(),
+                    0,
+                );
+            }
+            draw_buffers(colors.len());
+
+            // with msaa the depth only lives in the multisampled framebuffer, nothing reads it after
+            if !multisampled {
+                depth = self.depth_stencil.format().map(|(format, attachment)| {
+                    renderbuffer(format, attachment, 1, self.width, self.height)
+                });
+            }
+
+            let mut result = check_status();
+
+            if multisampled && result.is_ok() {
+                let mut ms_fbo = 0;
+                gl::GenFramebuffers(1, &mut ms_fbo);
+                ctx.bind_framebuffer(gl::FRAMEBUFFER, ms_fbo);
+
+                let ms_colors = (0..colors.len())
+                    .map(|i| {
+                        renderbuffer(
+                            gl::RGBA8,
+                            gl::COLOR_ATTACHMENT0 + i as u32,
+                            self.samples,
+                            self.width,
+                            self.height,
+                        )
+                    })
+                    .collect();
+                draw_buffers(colors.len());
+
+                let ms_depth = self.depth_stencil.format().map(|(format, attachment)| {
+                    renderbuffer(format, attachment, self.samples, self.width, self.height)
+                });
+
+                result = check_status();
+                msaa = Some(Msaa {
+                    fbo: ms_fbo,
+                    colors: ms_colors,
+                    depth: ms_depth,
+                });
+            }
+
+            ctx.bind_framebuffer(gl::FRAMEBUFFER, prev as u32);
+
+            // dropping the half made target cleans up whatever did get made
+            let target = RenderTarget {
+                config: self,
+                fbo,
+                depth,
+                msaa,
+                colors,
+                prev: Cell::new(None),
+                ctx,
+            };
+
+            result.map(|_| target)
+        }
+    }
+}
+
+unsafe fn draw_buffers(count: usize) {
+    let buffers = (0..count as u32)
+        .map(|i| gl::COLOR_ATTACHMENT0 + i)
+        .collect::<Vec<_>>();
+    gl::DrawBuffers(buffers.len() as i32, buffers.as_ptr());
+}
+
+unsafe fn renderbuffer(format: u32, attachment: u32, samples: u32, width: u32, height: u32) -> u32 {
+    let mut id = 0;
+    gl::GenRenderbuffers(1, &mut id);
+    gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+
+    if samples > 1 {
+        gl::RenderbufferStorageMultisample(
+            gl::RENDERBUFFER,
+            samples as i32,
+            format,
+            width as i32,
+            height as i32,
+        );
+    } else {
+        gl::RenderbufferStorage(gl::RENDERBUFFER, format, width as i32, height as i32);
+    }
+
+    gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER, id);
+    gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+    id
+}
+
+struct Msaa {
+    fbo: u32,
+    colors: Vec<u32>,
+    depth: Option<u32>,
+}
+
+// what was bound before bind, so unbind can put it back
+#[derive(Clone, Copy)]
+struct BindState {
+    framebuffer: u32,
+    viewport: [i32; 4],
+}
+
+// an offscreen framebuffer that renders into textures
+pub struct RenderTarget {
+    config: RenderTargetBuilder,
+    fbo: u32,
+    depth: Option<u32>,
+    msaa: Option<Msaa>,
+    colors: Vec<Texture>,
+    prev: Cell<Option<BindState>>,
+    ctx: Rc<GlContext>,
+}
+
+impl RenderTarget {
+    // one rgba color texture and a depth/stencil renderbuffer
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut builder = RenderTargetBuilder::new(width, height);
+        builder.add_color_attachment();
+        builder.set_depth_stencil(DepthStencil::DepthStencil);
+        builder.build().expect("Failed to make a render target")
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.config.samples
+    }
+
+    // the first color attachment
+    pub fn texture(&self) -> &Texture {
+        &self.colors[0]
+    }
+
+    pub fn textures(&self) -> &[Texture] {
+        &self.colors
+    }
+
+    // remakes every attachment at the new size, the old contents are lost
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), FramebufferError> {
+        if self.size() == (width, height) {
+            return Ok(());
+        }
+
+        let mut config = self.config.clone();
+        config.width = width;
+        config.height = height;
+        *self = config.build()?;
+        Ok(())
+    }
+
+    // hook this up with Window::on_resize so the target follows the framebuffer, a minimized
+    // window keeps the old size
+    pub fn follow(&mut self, resize: &Resize) -> Result<(), FramebufferError> {
+        match resize.framebuffer_size {
+            (w, h) if w > 0 && h > 0 => self.resize(w as u32, h as u32),
+            _ => Ok(()),
+        }
+    }
+
+    // draws after this go into the target, the viewport is set to cover the whole target
+    pub fn bind(&self) {
+        let (w, h) = self.size();
+        let draw_fbo = self.msaa.as_ref().map_or(self.fbo, |m| m.fbo);
+
+        unsafe {
+            let mut framebuffer = 0;
+            let mut viewport = [0; 4];
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            self.prev.set(Some(BindState {
+                framebuffer: framebuffer as u32,
+                viewport,
+            }));
+
+            self.ctx.bind_framebuffer(gl::FRAMEBUFFER, draw_fbo);
+            gl::Viewport(0, 0, w as i32, h as i32);
+        }
+    }
+
+    // resolves msaa and puts back whatever framebuffer and viewport were there before bind
+    pub fn unbind(&self) {
+        self.resolve();
+
+        let prev = self.prev.take().unwrap_or(BindState {
+            framebuffer: 0,
+            viewport: [0, 0, self.config.width as i32, self.config.height as i32],
+        });
+        let [x, y, w, h] = prev.viewport;
+
+        self.ctx.bind_framebuffer(gl::FRAMEBUFFER, prev.framebuffer);
+        unsafe {
+            gl::Viewport(x, y, w, h);
+        }
+    }
+
+    // binds until the returned guard is dropped
+    pub fn bind_scoped(&self) -> BoundTarget<'_> {
+        self.bind();
+        BoundTarget { target: self }
+    }
+
+    // copies the multisampled attachments into the textures, does nothing without msaa
+    pub fn resolve(&self) {
+        let Some(msaa) = &self.msaa else {
+            return;
+        };
+
+        let (w, h) = (self.config.width as i32, self.config.height as i32);
+
+        unsafe {
+            let mut read = 0;
+            let mut draw = 0;
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut read);
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut draw);
+
+            self.ctx.bind_framebuffer(gl::READ_FRAMEBUFFER, msaa.fbo);
+            self.ctx.bind_framebuffer(gl::DRAW_FRAMEBUFFER, self.fbo);
+
+            // blits only go from one read buffer to the draw buffers, so do one attachment at a time
+            for i in 0..self.colors.len() as u32 {
+                gl::ReadBuffer(gl::COLOR_ATTACHMENT0 + i);
+                gl::DrawBuffer(gl::COLOR_ATTACHMENT0 + i);
+                gl::BlitFramebuffer(0, 0, w, h, 0, 0, w, h, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            }
+
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+            draw_buffers(self.colors.len());
+
+            self.ctx.bind_framebuffer(gl::READ_FRAMEBUFFER, read as u32);
+            self.ctx.bind_framebuffer(gl::DRAW_FRAMEBUFFER, draw as u32);
+        }
+    }
+
+    // top row first, alpha is kept as whatever was drawn
+    pub fn read_pixels(&self) -> RgbaImage {
+        readback::read_pixels(self)
+    }
+}
+
+impl PixelSource for RenderTarget {
+    fn read_size(&self) -> (u32, u32) {
+        self.size()
+    }
+
+    fn bind_read(&self) {
+        self.ctx.bind_framebuffer(gl::READ_FRAMEBUFFER, self.fbo);
+        unsafe {
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+        }
+    }
+
+    fn keeps_alpha(&self) -> bool {
+        true
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        if let Some(msaa) = &self.msaa {
+            for color in &msaa.colors {
+                self.ctx.delete_renderbuffer(*color);
+            }
+            if let Some(depth) = msaa.depth {
+                self.ctx.delete_renderbuffer(depth);
+            }
+            self.ctx.delete_framebuffer(msaa.fbo);
+        }
+
+        if let Some(depth) = self.depth {
+            self.ctx.delete_renderbuffer(depth);
+        }
+        self.ctx.delete_framebuffer(self.fbo);
+        // offscreen rendering might never swap buffers. the color textures would only queue
+        // their deletes after this when the fields drop, so they go first
+        self.colors.clear();
+        self.ctx.flush_deletes();
+    }
+}
+
+pub struct BoundTarget<'a> {
+    target: &'a RenderTarget,
+}
+
+impl Drop for BoundTarget<'_> {
+    fn drop(&mut self) {
+        self.target.unbind();
+    }
+}
@@ -0,0 +1,467 @@
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use image::{Rgba, RgbaImage};
+
+use crate::math::color::Color;
+
+use super::framebuffer::RenderTarget;
+#[cfg(feature = "egl")]
+use super::headless::HeadlessContext;
+use super::readback::save_image;
+use super::window::Window;
+
+// glfw and gl contexts dont like being used from several test threads at once
+static GL_LOCK: Mutex<()> = Mutex::new(());
+
+// hold this in any test that makes a window, a test that panicked while holding it
+// doesnt stop the rest
+pub fn lock_gl() -> MutexGuard<'static, ()> {
+    GL_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    // biggest difference allowed in any one channel before a pixel counts as different
+    pub channel: u8,
+    // biggest yiq color difference allowed per pixel, 0 is identical and 1 is the most two
+    // colors can differ, black vs white is about 0.93
+    pub perceptual: f32,
+    // how many pixels (0 to 1) are allowed to be different before the test fails
+    pub max_different: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        // a little slack for drivers rasterizing edges slightly differently
+        Self {
+            channel: 2,
+            perceptual: 0.01,
+            max_different: 0.001,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GoldenDiff {
+    pub size_mismatch: bool,
+    pub different_pixels: usize,
+    pub total_pixels: usize,
+    pub max_channel_delta: u8,
+    pub max_perceptual_delta: f32,
+    pub mean_perceptual_delta: f32,
+    // red where pixels are over the tolerance, yellow where they changed but are still within it
+    pub diff: RgbaImage,
+}
+
+impl GoldenDiff {
+    pub fn different_ratio(&self) -> f32 {
+        self.different_pixels as f32 / self.total_pixels.max(1) as f32
+    }
+
+    pub fn passes(&self, tolerance: &Tolerance) -> bool {
+        !self.size_mismatch && self.different_ratio() <= tolerance.max_different
+    }
+}
+
+pub fn compare(actual: &RgbaImage, expected: &RgbaImage, tolerance: &Tolerance) -> GoldenDiff {
+    if actual.dimensions() != expected.dimensions() {
+        return GoldenDiff {
+            size_mismatch: true,
+            different_pixels: expected.pixels().len(),
+            total_pixels: expected.pixels().len(),
+            max_channel_delta: 255,
+            max_perceptual_delta: 1.0,
+            mean_perceptual_delta: 1.0,
+            diff: RgbaImage::from_pixel(actual.width(), actual.height(), Rgba([255, 0, 0, 255])),
+        };
+    }
+
+    let (w, h) = expected.dimensions();
+    let mut diff = RgbaImage::new(w, h);
+    let mut different_pixels = 0;
+    let mut max_channel_delta = 0;
+    let mut max_perceptual_delta = 0f32;
+    let mut total_perceptual = 0f64;
+
+    for (x, y, e) in expected.enumerate_pixels() {
+        let a = actual.get_pixel(x, y);
+
+        let channel = (0..4).map(|i| a.0[i].abs_diff(e.0[i])).max().unwrap_or(0);
+        let perceptual = perceptual_delta(a, e);
+
+        max_channel_delta = max_channel_delta.max(channel);
+        max_perceptual_delta = max_perceptual_delta.max(perceptual);
+        total_perceptual += perceptual as f64;
+
+        let out = if channel > tolerance.channel || perceptual > tolerance.perceptual {
+            different_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else if channel > 0 {
+            Rgba([255, 255, 0, 255])
+        } else {
+            // faded copy of the expected image so the differences stand out
+            let l = (255.0 - 0.1 * (255.0 - luma(e))) as u8;
+            Rgba([l, l, l, 255])
+        };
+        diff.put_pixel(x, y, out);
+    }
+
+    let total_pixels = (w * h) as usize;
+
+    GoldenDiff {
+        size_mismatch: false,
+        different_pixels,
+        total_pixels,
+        max_channel_delta,
+        max_perceptual_delta,
+        mean_perceptual_delta: (total_perceptual / total_pixels.max(1) as f64) as f32,
+        diff,
+    }
+}
+
+// blend onto white so transparent pixels compare by what you would actually see
+fn blend(p: &Rgba<u8>) -> [f32; 3] {
+    let a = p.0[3] as f32 / 255.0;
+    [0, 1, 2].map(|i| 255.0 + (p.0[i] as f32 - 255.0) * a)
+}
+
+fn luma(p: &Rgba<u8>) -> f32 {
+    let [r, g, b] = blend(p);
+    r * 0.2988953 + g * 0.5866225 + b * 0.1144822
+}
+
+// the yiq color distance from pixelmatch, scaled to 0..1
+pub fn perceptual_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    // largest possible value of the delta below (pixelmatch's constant), black against
+    // white only gets to 0.5053 * 255^2
+    const MAX_DELTA: f32 = 35215.0;
+
+    let yiq = |[r, g, b]: [f32; 3]| {
+        [
+            r * 0.2988953 + g * 0.5866225 + b * 0.1144822,
+            r * 0.595978 - g * 0.2741761 - b * 0.3218019,
+            r * 0.2114702 - g * 0.5226171 + b * 0.3111469,
+        ]
+    };
+
+    let [y1, i1, q1] = yiq(blend(a));
+    let [y2, i2, q2] = yiq(blend(b));
+    let (y, i, q) = (y1 - y2, i1 - i2, q1 - q2);
+
+    (0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / MAX_DELTA
+}
+
+fn reference_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+fn output_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("target")
+        .join("golden")
+}
+
+// QRGL_BLESS=1 cargo test writes the current output as the new references
+pub fn blessing() -> bool {
+    std::env::var("QRGL_BLESS").is_ok_and(|v| v != "0")
+}
+
+// compares against tests/golden/<name>.png, on failure the actual, expected and diff
+// images are written to target/golden so you can look at what changed
+pub fn assert_golden(name: &str, actual: &RgbaImage, tolerance: &Tolerance) {
+    let reference = reference_dir().join(format!("{name}.png"));
+
+    if blessing() {
+        std::fs::create_dir_all(reference_dir()).expect("Could not make the golden directory");
+        save_image(actual, &reference).expect("Could not write the golden image");
+        return;
+    }
+
+    let expected = match image::open(&reference) {
+        Ok(image) => image.into_rgba8(),
+        Err(e) => panic!(
+            "No golden image for {name} at {} ({e}), run with QRGL_BLESS=1 to make it",
+            reference.display()
+        ),
+    };
+
+    let result = compare(actual, &expected, tolerance);
+    if result.passes(tolerance) {
+        return;
+    }
+
+    let out = output_dir();
+    std::fs::create_dir_all(&out).expect("Could not make the golden output directory");
+    save_image(actual, out.join(format!("{name}.actual.png"))).unwrap();
+    save_image(&expected, out.join(format!("{name}.expected.png"))).unwrap();
+    save_image(&result.diff, out.join(format!("{name}.diff.png"))).unwrap();
+
+    panic!(
+        "Golden image {name} does not match: {} of {} pixels differ ({:.3}%), size mismatch: {}, \
+         max channel delta: {}, max perceptual delta: {:.4}, mean perceptual delta: {:.6}, \
+         see {}",
+        result.different_pixels,
+        result.total_pixels,
+        result.different_ratio() * 100.0,
+        result.size_mismatch,
+        result.max_channel_delta,
+        result.max_perceptual_delta,
+        result.mean_perceptual_delta,
+        out.display()
+    );
+}
+
+// clears a render target to the background and runs draw with the target bound, then reads
+// the frame back. with the egl feature it all happens in a context that needs no display,
+// otherwise in a hidden window
+pub fn render<F: FnOnce()>(width: u32, height: u32, background: Color, draw: F) -> RgbaImage {
+    let _lock = lock_gl();
+
+    #[cfg(feature = "egl")]
+    let _context = HeadlessContext::new().expect("Failed to make a headless context");
+    #[cfg(not(feature = "egl"))]
+    let _window = Window::headless(width, height);
+    let target = RenderTarget::new(width, height);
+
+    target.bind();
+    let [r, g, b, a] = background.to_rgba_f32();
+    unsafe {
+        gl::ClearColor(r, g, b, a);
+        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+    }
+    draw();
+    target.unbind();
+
+    target.read_pixels()
+}
+
+#[cfg(test)]
+fn cornflower() -> Color {
+    Color::rgb(100, 149, 237)
+}
+
+#[cfg(test)]
+fn draw_shape<S: super::shapes::Shape>(shape: S, color: Color) {
+    use super::ogl::{DrawStream, Material};
+    use crate::math::transform::Transform2D;
+
+    let stream = DrawStream::from(shape);
+    let mut material = Material::default();
+    material.set_color(color);
+    stream.draw(&material, &Transform2D::default());
+}
+
+#[test]
+fn test_compare_identical() {
+    let a = RgbaImage::from_pixel(4, 4, Rgba([10, 20, 30, 255]));
+    let result = compare(&a, &a.clone(), &Tolerance::default());
+
+    assert!(result.passes(&Tolerance::default()));
+    assert_eq!(result.different_pixels, 0);
+    assert_eq!(result.max_channel_delta, 0);
+}
+
+#[test]
+fn test_compare_tolerance() {
+    let a = RgbaImage::from_pixel(10, 10, Rgba([100, 100, 100, 255]));
+    let mut b = a.clone();
+
+    // one channel off by one is within the default tolerance
+    b.put_pixel(0, 0, Rgba([101, 100, 100, 255]));
+    let result = compare(&b, &a, &Tolerance::default());
+    assert_eq!(result.different_pixels, 0);
+    assert_eq!(result.max_channel_delta, 1);
+    assert_eq!(result.diff.get_pixel(0, 0), &Rgba([255, 255, 0, 255]));
+
+    // a completely different pixel is 1 in 100 which is more than the default allows
+    b.put_pixel(5, 5, Rgba([255, 0, 0, 255]));
+    let result = compare(&b, &a, &Tolerance::default());
+    assert_eq!(result.different_pixels, 1);
+    assert!(!result.passes(&Tolerance::default()));
+    assert_eq!(result.diff.get_pixel(5, 5), &Rgba([255, 0, 0, 255]));
+
+    let loose = Tolerance {
+        max_different: 0.02,
+        ..Tolerance::default()
+    };
+    assert!(result.passes(&loose));
+}
+
+#[test]
+fn test_compare_size_mismatch() {
+    let a = RgbaImage::new(4, 4);
+    let b = RgbaImage::new(4, 5);
+    assert!(!compare(&a, &b, &Tolerance::default()).passes(&Tolerance::default()));
+}
+
+#[test]
+fn test_perceptual_delta() {
+    let black = Rgba([0, 0, 0, 255]);
+    let white = Rgba([255, 255, 255, 255]);
+    let clear = Rgba([0, 0, 0, 0]);
+
+    // black and white only differ in brightness, colors far apart in i and q go higher
+    let delta = perceptual_delta(&black, &white);
+    assert!((delta - 0.933).abs() < 1e-3);
+    assert_eq!(perceptual_delta(&black, &black), 0.0);
+    // fully transparent is seen as white
+    assert!(perceptual_delta(&clear, &white) < 1e-6);
+}
+
+// the references in tests/golden are rendered by mesa's llvmpipe through the egl feature,
+// other drivers can rasterize edges a little differently. remake them with
+// `QRGL_BLESS=1 cargo test --features egl golden` and look at what changed before committing
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn golden_square() {
+    use super::shapes::Square;
+    use crate::math::vector::vec2;
+
+    let image = render(128, 128, cornflower(), || {
+        draw_shape(
+            Square::new(vec2(-0.5, -0.5), vec2(1.0, 1.0)),
+            Color::rgb(255, 255, 0),
+        );
+    });
+    assert_golden("square", &image, &Tolerance::default());
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn golden_triangle() {
+    use super::shapes::Triangle;
+    use crate::math::vector::vec2;
+
+    let image = render(128, 128, cornflower(), || {
+        draw_shape(
+            Triangle::new(vec2(0., 0.8), vec2(-0.8, -0.8), vec2(0.8, -0.8)),
+            Color::rgb(255, 0, 255),
+        );
+    });
+    assert_golden("triangle", &image, &Tolerance::default());
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn golden_circle() {
+    use super::shapes::Circle;
+    use crate::math::vector::vec2;
+
+    let image = render(128, 128, cornflower(), || {
+        draw_shape(Circle::new(vec2(0., 0.), 0.8), Color::rgb(0, 255, 255));
+    });
+    assert_golden("circle", &image, &Tolerance::default());
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn golden_scene() {
+    use super::shapes::{Circle, Square, Triangle};
+    use crate::math::vector::vec2;
+
+    let image = render(256, 256, cornflower(), || {
+        draw_shape(
+            Square::new(vec2(-0.25, -0.9), vec2(0.5, 0.5)),
+            Color::rgb(255, 255, 0),
+        );
+        draw_shape(Circle::new(vec2(0., 0.3), 0.3), Color::rgb(0, 255, 255));
+        draw_shape(
+            Triangle::new(vec2(0., 0.9), vec2(-0.5, 0.4), vec2(0.5, 0.4)),
+            Color::rgb(255, 0, 255),
+        );
+    });
+    assert_golden("scene", &image, &Tolerance::default());
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn golden_gradient() {
+    use super::ogl::{DrawStream, Material};
+    use super::shapes::{Circle, Gradient, Square};
+    use crate::math::transform::Transform2D;
+    use crate::math::vector::vec2;
+
+    let image = render(128, 128, cornflower(), || {
+        let material = Material::vertex_colors();
+        let transform = Transform2D::default();
+
+        let square = Square::new(vec2(-0.9, -0.9), vec2(0.8, 0.8)).with_corner_colors([
+            Color::RED,
+            Color::GREEN,
+            Color::BLUE,
+            Color::WHITE,
+        ]);
+        DrawStream::from(square).draw(&material, &transform);
+
+        let linear = Gradient::linear(vec2(0.1, 0.), vec2(0.9, 0.), Color::RED, Color::BLUE)
+            .with_stop(0.5, Color::GREEN)
+            .fill(Square::new(vec2(0.1, -0.9), vec2(0.8, 0.8)));
+        DrawStream::from(linear).draw(&material, &transform);
+
+        let radial = Gradient::radial(vec2(0., 0.5), 0.4, Color::WHITE, Color::BLACK)
+            .fill(Circle::new(vec2(0., 0.5), 0.4).fit_to_screen(64.));
+        DrawStream::from(radial).draw(&material, &transform);
+    });
+    assert_golden("gradient", &image, &Tolerance::default());
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn golden_transform() {
+    use super::ogl::{DrawStream, Material};
+    use super::shapes::Square;
+    use crate::math::transform::Transform2D;
+    use crate::math::vector::vec2;
+
+    let image = render(128, 128, cornflower(), || {
+        let mut material = Material::default();
+        material.set_color(Color::rgb(255, 255, 0));
+
+        // one unit square uploaded once, drawn three times turning around its middle
+        let stream = DrawStream::from(Square::new(vec2(0., 0.), vec2(1., 1.)));
+        for i in 0..3 {
+            let transform = Transform2D::new(vec2(-0.6 + i as f32 * 0.6, 0.))
+                .with_origin(vec2(0.5, 0.5))
+                .with_rotation(i as f32 * std::f32::consts::PI / 8.)
+                .with_scale(vec2(0.4, 0.4));
+            stream.draw(&material, &transform);
+        }
+    });
+    assert_golden("transform", &image, &Tolerance::default());
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn golden_instanced() {
+    use super::instance::{Instance, InstanceBuffer};
+    use super::ogl::{DrawStream, Material};
+    use super::shapes::Square;
+    use crate::math::transform::Transform2D;
+    use crate::math::vector::vec2;
+
+    let image = render(128, 128, cornflower(), || {
+        let material = Material::instanced();
+        let stream = DrawStream::from(Square::new(vec2(0., 0.), vec2(1., 1.)));
+
+        // a checkerboard of 16 by 16 tiles in one draw
+        let mut instances = InstanceBuffer::with_capacity(256);
+        for y in 0..16 {
+            for x in 0..16 {
+                let color = if (x + y) % 2 == 0 {
+                    Color::rgb(255, 255, 0)
+                } else {
+                    Color::rgb(255, 0, 255)
+                };
+                let transform = Transform2D::new(vec2(-1. + x as f32 / 8., -1. + y as f32 / 8.))
+                    .with_scale(vec2(1. / 8., 1. / 8.));
+                instances.push(Instance::new(&transform).with_color(color));
+            }
+        }
+        stream.draw_instanced(&material, &instances);
+    });
+    assert_golden("instanced", &image, &Tolerance::default());
+}
@@ -0,0 +1,193 @@
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fmt::Display;
+
+use super::context::GlContext;
+
+// a gl context with no window and no display at all, made on egl's surfaceless platform.
+// mesa's llvmpipe runs it without a gpu, that is what ci and the golden tests render with.
+// there is nothing to show it on, draw into a RenderTarget and read the pixels back
+
+type EglDisplay = *mut c_void;
+type EglContext = *mut c_void;
+
+const EGL_EXTENSIONS: i32 = 0x3055;
+const EGL_NONE: i32 = 0x3038;
+const EGL_OPENGL_API: u32 = 0x30A2;
+const EGL_CONTEXT_MAJOR_VERSION: i32 = 0x3098;
+const EGL_CONTEXT_MINOR_VERSION: i32 = 0x30FB;
+const EGL_CONTEXT_OPENGL_PROFILE_MASK: i32 = 0x30FD;
+const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: i32 = 1;
+const EGL_PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
+
+#[link(name = "EGL")]
+extern "C" {
+    fn eglGetProcAddress(name: *const c_char) -> *const c_void;
+    fn eglGetError() -> i32;
+    fn eglQueryString(display: EglDisplay, name: i32) -> *const c_char;
+    fn eglInitialize(display: EglDisplay, major: *mut i32, minor: *mut i32) -> u32;
+    fn eglBindAPI(api: u32) -> u32;
+    fn eglCreateContext(
+        display: EglDisplay,
+        config: *mut c_void,
+        share: EglContext,
+        attributes: *const i32,
+    ) -> EglContext;
+    fn eglDestroyContext(display: EglDisplay, context: EglContext) -> u32;
+    fn eglMakeCurrent(
+        display: EglDisplay,
+        draw: *mut c_void,
+        read: *mut c_void,
+        context: EglContext,
+    ) -> u32;
+    fn eglGetCurrentContext() -> EglContext;
+}
+
+type GetPlatformDisplay =
+    unsafe extern "C" fn(platform: u32, native: *mut c_void, attributes: *const i32) -> EglDisplay;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadlessError {
+    // the egl library doesnt know the surfaceless platform, it is a mesa extension
+    NoSurfaceless,
+    // the rest carry what eglGetError said
+    Initialize(i32),
+    CreateContext(i32),
+    MakeCurrent(i32),
+}
+
+impl Display for HeadlessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeadlessError::NoSurfaceless => {
+                write!(
+                    f,
+                    "EGL has no surfaceless platform (EGL_MESA_platform_surfaceless)"
+                )
+            }
+            HeadlessError::Initialize(error) => {
+                write!(f, "Failed to initialize the EGL display (0x{error:X})")
+            }
+            HeadlessError::CreateContext(error) => {
+                write!(f, "Failed to make an OpenGL 3.3 core context (0x{error:X})")
+            }
+            HeadlessError::MakeCurrent(error) => {
+                write!(
+                    f,
+                    "Failed to make the headless context current (0x{error:X})"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+pub struct HeadlessContext {
+    display: EglDisplay,
+    context: EglContext,
+}
+
+impl HeadlessContext {
+    // the same gl version a Glfw window gets, made current right away
+    pub fn new() -> Result<Self, HeadlessError> {
+        unsafe {
+            let client = eglQueryString(std::ptr::null_mut(), EGL_EXTENSIONS);
+            let surfaceless = !client.is_null()
+                && CStr::from_ptr(client)
+                    .to_string_lossy()
+                    .split(' ')
+                    .any(|e| e == "EGL_MESA_platform_surfaceless");
+            let get_display = proc_address("eglGetPlatformDisplayEXT");
+            if !surfaceless || get_display.is_null() {
+                return Err(HeadlessError::NoSurfaceless);
+            }
+
+            let get_display: GetPlatformDisplay = std::mem::transmute(get_display);
+            let display = get_display(
+                EGL_PLATFORM_SURFACELESS_MESA,
+                std::ptr::null_mut(),
+                [EGL_NONE].as_ptr(),
+            );
+            let (mut major, mut minor) = (0, 0);
+            if display.is_null() || eglInitialize(display, &mut major, &mut minor) == 0 {
+                return Err(HeadlessError::Initialize(eglGetError()));
+            }
+
+            eglBindAPI(EGL_OPENGL_API);
+            let attributes = [
+                EGL_CONTEXT_MAJOR_VERSION,
+                3,
+                EGL_CONTEXT_MINOR_VERSION,
+                3,
+                EGL_CONTEXT_OPENGL_PROFILE_MASK,
+                EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                EGL_NONE,
+            ];
+            // no config and no surface, everything gets drawn into framebuffer objects
+            let context = eglCreateContext(
+                display,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                attributes.as_ptr(),
+            );
+            if context.is_null() {
+                return Err(HeadlessError::CreateContext(eglGetError()));
+            }
+
+            let headless = Self { display, context };
+            headless.make_current()?;
+            gl::load_with(proc_address);
+            GlContext::register(context as usize, None);
+            Ok(headless)
+        }
+    }
+
+    pub fn make_current(&self) -> Result<(), HeadlessError> {
+        GlContext::flush_current();
+        let made = unsafe {
+            eglMakeCurrent(
+                self.display,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                self.context,
+            )
+        };
+        if made == 0 {
+            return Err(HeadlessError::MakeCurrent(unsafe { eglGetError() }));
+        }
+        Ok(())
+    }
+
+    pub fn is_current(&self) -> bool {
+        current_context() == self.context as usize
+    }
+}
+
+// the display stays initialized, egl hands every context on this platform the same one
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        unsafe {
+            if self.is_current() {
+                GlContext::current().flush_deletes();
+                eglMakeCurrent(
+                    self.display,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                );
+            }
+            GlContext::retire(self.context as usize);
+            eglDestroyContext(self.display, self.context);
+        }
+    }
+}
+
+// what GlContext keys the egl context by when no glfw window is current
+pub(crate) fn current_context() -> usize {
+    unsafe { eglGetCurrentContext() as usize }
+}
+
+fn proc_address(name: &str) -> *const c_void {
+    let name = CString::new(name).unwrap();
+    unsafe { eglGetProcAddress(name.as_ptr()) }
+}
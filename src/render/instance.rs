@@ -0,0 +1,216 @@
+use std::cell::{Cell, RefCell};
+
+use crate::math::color::Color;
+use crate::math::matrix::Mat3d;
+use crate::math::transform::Transform;
+
+use super::ogl::{BufferUsage, Vbo};
+use super::sprite::UvRect;
+
+// per instance data for DrawStream::draw_instanced. the attributes sit at fixed locations
+// after anything a mesh could use, so they work with every shape's vertex layout:
+// 8-11 model matrix columns, 12 color, 13 uv rect as u0 v0 u1 v1
+pub const INSTANCE_LOCATION: u32 = 8;
+
+// model 16, color 4, uv 4
+const INSTANCE_FLOATS: usize = 24;
+const INSTANCE_VEC4S: usize = INSTANCE_FLOATS / 4;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Instance {
+    pub model: Mat3d,
+    pub color: Color,
+    // the mesh's 0 to 1 positions are mapped into this for the texture
+    pub uv: UvRect,
+}
+
+impl Instance {
+    pub fn new(transform: &impl Transform) -> Self {
+        Self {
+            model: transform.matrix(),
+            color: Color::WHITE,
+            uv: UvRect::FULL,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_uv(mut self, uv: UvRect) -> Self {
+        self.uv = uv;
+        self
+    }
+
+    fn pack(&self, out: &mut [f32]) {
+        for (i, column) in self.model.to_cols().iter().enumerate() {
+            out[i * 4..i * 4 + 4].copy_from_slice(column);
+        }
+        out[16..20].copy_from_slice(&self.color.to_rgba_f32());
+        out[20..24].copy_from_slice(&[self.uv.u0, self.uv.v0, self.uv.u1, self.uv.v1]);
+    }
+}
+
+// instances are kept on the cpu and only uploaded when they changed since the last draw
+#[derive(Default)]
+pub struct InstanceBuffer {
+    data: Vec<f32>,
+    // made on the first upload so the buffer can be filled before there is a context
+    vbo: RefCell<Option<Vbo>>,
+    dirty: Cell<bool>,
+}
+
+impl InstanceBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(instances: usize) -> Self {
+        Self {
+            data: Vec::with_capacity(instances * INSTANCE_FLOATS),
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, instance: Instance) {
+        let start = self.data.len();
+        self.data.resize(start + INSTANCE_FLOATS, 0.);
+        instance.pack(&mut self.data[start..]);
+        self.dirty.set(true);
+    }
+
+    pub fn set(&mut self, index: usize, instance: Instance) {
+        let start = index * INSTANCE_FLOATS;
+        instance.pack(&mut self.data[start..start + INSTANCE_FLOATS]);
+        self.dirty.set(true);
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.dirty.set(true);
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len() / INSTANCE_FLOATS
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // uploads if needed and points the instance attributes of the bound vao at the buffer
+    pub(crate) fn bind_attributes(&self) {
+        let mut vbo = self.vbo.borrow_mut();
+        let vbo = vbo.get_or_insert_with(|| {
+            self.dirty.set(true);
+            Vbo::with_usage(BufferUsage::Dynamic)
+        });
+        if self.dirty.replace(false) {
+            // reuses the storage when it fits, orphaned so last frame's draw can finish
+            if self.data.len() <= vbo.capacity() {
+                vbo.orphan();
+                vbo.set_sub_data(0, &self.data);
+            } else {
+                vbo.set_data(&self.data);
+            }
+        }
+        vbo.bind();
+
+        let stride = (INSTANCE_FLOATS * std::mem::size_of::<f32>()) as i32;
+        for i in 0..INSTANCE_VEC4S {
+            let location = INSTANCE_LOCATION + i as u32;
+            let offset = i * 4 * std::mem::size_of::<f32>();
+            unsafe {
+                gl::EnableVertexAttribArray(location);
+                gl::VertexAttribPointer(
+                    location,
+                    4,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    stride,
+                    offset as *const _,
+                );
+                gl::VertexAttribDivisor(location, 1);
+            }
+        }
+    }
+
+    // the attributes were put on the mesh's own vao, this takes them off again after the
+    // draw so a plain draw of the same mesh doesnt still step through instances
+    pub(crate) fn unbind_attributes(&self) {
+        for i in 0..INSTANCE_VEC4S {
+            let location = INSTANCE_LOCATION + i as u32;
+            unsafe {
+                gl::DisableVertexAttribArray(location);
+                gl::VertexAttribDivisor(location, 0);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_instance_packing() {
+    use crate::math::transform::Transform2D;
+    use crate::math::vector::vec2;
+
+    let mut buffer = InstanceBuffer::new();
+    assert!(buffer.is_empty());
+
+    for i in 0..3 {
+        buffer.push(
+            Instance::new(&Transform2D::new(vec2(i as f32, 0.)))
+                .with_color(Color::RED)
+                .with_uv(UvRect::new(0., 0., 0.5, 0.25)),
+        );
+    }
+    assert_eq!(buffer.len(), 3);
+
+    let last = &buffer.data[2 * INSTANCE_FLOATS..];
+    // the translation is the last column of the model
+    assert_eq!(last[12..16], [2., 0., 0., 1.]);
+    assert_eq!(last[16..20], [1., 0., 0., 1.]);
+    assert_eq!(last[20..24], [0., 0., 0.5, 0.25]);
+
+    buffer.set(0, Instance::new(&Mat3d::identity()));
+    assert_eq!(buffer.data[..4], [1., 0., 0., 0.]);
+    assert_eq!(buffer.data[20..24], [0., 0., 1., 1.]);
+
+    buffer.clear();
+    assert!(buffer.is_empty());
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn test_attributes_reset() {
+    use super::golden::render;
+    use super::ogl::{DrawStream, Material};
+    use super::shapes::Square;
+    use crate::math::transform::Transform2D;
+    use crate::math::vector::vec2;
+
+    render(4, 4, Color::BLACK, || {
+        let stream = DrawStream::from(Square::new(vec2(0., 0.), vec2(1., 1.)));
+        let mut instances = InstanceBuffer::new();
+        instances.push(Instance::new(&Transform2D::default()));
+        stream.draw_instanced(&Material::instanced(), &instances);
+
+        // the stream's vao is still bound and has none of the instance attributes left
+        for i in 0..INSTANCE_VEC4S as u32 {
+            let (mut enabled, mut divisor) = (1, 1);
+            unsafe {
+                gl::GetVertexAttribiv(
+                    INSTANCE_LOCATION + i,
+                    gl::VERTEX_ATTRIB_ARRAY_ENABLED,
+                    &mut enabled,
+                );
+                gl::GetVertexAttribiv(
+                    INSTANCE_LOCATION + i,
+                    gl::VERTEX_ATTRIB_ARRAY_DIVISOR,
+                    &mut divisor,
+                );
+            }
+            assert_eq!((enabled, divisor), (0, 0));
+        }
+    });
+}
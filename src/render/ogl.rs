@@ -1,7 +1,9 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::ffi::{CStr, CString};
 use std::hash::Hash;
 use std::iter::FromIterator;
+use std::rc::Rc;
 
 pub trait Bindable {
     fn bind(&self);
@@ -11,85 +13,102 @@ pub trait Uniform {
     fn apply_uniform(&mut self, loc: i32);
 }
 
+impl Uniform for f32 {
+    fn apply_uniform(&mut self, loc: i32) {
+        unsafe { gl::Uniform1f(loc, *self) }
+    }
+}
+
+// also used for sampler uniforms, the value is the texture unit
+impl Uniform for i32 {
+    fn apply_uniform(&mut self, loc: i32) {
+        unsafe { gl::Uniform1i(loc, *self) }
+    }
+}
+
+use bytemuck::Pod;
 use gl::*;
 
 use crate::math::color::Color;
+use crate::math::matrix::Mat3d;
+use crate::math::transform::Transform;
 use crate::math::vector::*;
 use crate::render::shaders;
 
 use crate::math::*;
 
+use super::context::GlContext;
+use super::instance::InstanceBuffer;
 use super::shapes::Shape;
 
-// TODO: we need to put more meta data on the buffer types because they do not store enough information to make them more efficient
+pub use super::buffer::{BufferUsage, Ebo, Vbo};
 
-pub struct Vbo {
-    id: u32,
-    size: usize,
+pub trait VertexInfo {
+    fn dims(&self) -> u32;
+    fn kind(&self) -> u32;
+    fn size(&self) -> usize;
+    fn normalized(&self) -> u8;
 }
 
-impl Vbo {
-    pub fn new() -> Self {
-        let vbo = unsafe {
-            let mut v = 0;
-            gl::GenBuffers(1, &mut v);
-            v
-        };
-        Self { id: vbo, size: 0 }
+pub struct VertexPosInfo2D;
+impl VertexInfo for VertexPosInfo2D {
+    fn dims(&self) -> u32 {
+        2
     }
 
-    pub fn new_bind() -> Self {
-        let v = Vbo::new();
-        v.bind();
-        v
+    fn size(&self) -> usize {
+        std::mem::size_of::<f32>() * self.dims() as usize
     }
 
-    pub fn new_bind_buffer<T>(data: &[T]) -> Self {
-        let mut v = Vbo::new();
-        v.set_data(&data);
-        v
+    fn kind(&self) -> u32 {
+        gl::FLOAT
     }
 
-    pub fn get_size(&self) -> usize {
-        self.size
+    fn normalized(&self) -> u8 {
+        gl::FALSE
     }
+}
 
-    pub fn set_data<T>(&mut self, data: &[T]) {
-        unsafe {
-            self.bind();
-            self.size = data.len();
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (std::mem::size_of::<T>() * self.size) as isize,
-                data.as_ptr().cast(),
-                gl::STATIC_DRAW,
-            );
-        }
+pub struct VertexColorInfo;
+impl VertexInfo for VertexColorInfo {
+    fn dims(&self) -> u32 {
+        4
     }
 
-    pub fn bind(&self) {
-        unsafe { gl::BindBuffer(gl::ARRAY_BUFFER, self.id) }
+    fn size(&self) -> usize {
+        std::mem::size_of::<f32>() * self.dims() as usize
     }
-}
 
-impl Drop for Vbo {
-    fn drop(&mut self) {
-        unsafe {
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::DeleteBuffers(1, &self.id)
-        }
+    fn kind(&self) -> u32 {
+        gl::FLOAT
+    }
+
+    fn normalized(&self) -> u8 {
+        gl::FALSE
     }
 }
 
-pub trait VertexInfo {
-    fn dims(&self) -> u32;
-    fn kind(&self) -> u32;
-    fn size(&self) -> usize;
-    fn normalized(&self) -> u8;
+pub struct VertexPosInfo3D;
+impl VertexInfo for VertexPosInfo3D {
+    fn dims(&self) -> u32 {
+        3
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of::<f32>() * self.dims() as usize
+    }
+
+    fn kind(&self) -> u32 {
+        gl::FLOAT
+    }
+
+    fn normalized(&self) -> u8 {
+        gl::FALSE
+    }
 }
 
-pub struct VertexPosInfo2D;
-impl VertexInfo for VertexPosInfo2D {
+pub struct VertexUvInfo;
+impl VertexInfo for VertexUvInfo {
     fn dims(&self) -> u32 {
         2
     }
@@ -107,10 +126,10 @@ impl VertexInfo for VertexPosInfo2D {
     }
 }
 
-pub struct VertexColorInfo;
-impl VertexInfo for VertexColorInfo {
+pub struct VertexRgbaInfo;
+impl VertexInfo for VertexRgbaInfo {
     fn dims(&self) -> u32 {
-        3
+        4
     }
 
     fn size(&self) -> usize {
@@ -129,6 +148,7 @@ impl VertexInfo for VertexColorInfo {
 pub struct Vao {
     id: u32,
     info: Vec<&'static dyn VertexInfo>,
+    ctx: Rc<GlContext>,
 }
 
 impl Vao {
@@ -140,6 +160,7 @@ impl Vao {
                 vao
             },
             info: Vec::new(),
+            ctx: GlContext::current(),
         }
     }
 
@@ -150,14 +171,14 @@ impl Vao {
     }
 
     pub fn bind(&self) {
-        unsafe { gl::BindVertexArray(self.id) }
+        self.ctx.bind_vao(self.id);
     }
 
     pub fn unbind(&self) {
-        unsafe { gl::BindVertexArray(0) }
+        self.ctx.bind_vao(0);
     }
 
-    pub fn add_attribute<T: VertexInfo>(&mut self, vbo: &Vbo, data: &'static T) {
+    pub fn add_attribute<T: VertexInfo, V: Pod>(&mut self, vbo: &Vbo<V>, data: &'static T) {
         self.bind();
         vbo.bind();
         self.info.push(data);
@@ -167,6 +188,8 @@ impl Vao {
             stride += data.size() as i32;
         }
 
+        // attributes can be different sizes, so the offset is everything that came before it
+        let mut offset = 0;
         for (i, data) in self.info.iter().enumerate() {
             unsafe {
                 gl::EnableVertexAttribArray(i as u32);
@@ -176,110 +199,66 @@ impl Vao {
                     data.kind(),
                     data.normalized(),
                     stride as i32,
-                    (i * data.size()) as *const _,
+                    offset as *const _,
                 );
             }
+            offset += data.size();
         }
     }
 }
 
 impl Drop for Vao {
+    // the context only unbinds it when it really goes away, and only if it is bound
     fn drop(&mut self) {
-        unsafe {
-            // TODO! These can be potentially buggy.
-            // just because we are dropping a vao, doesnt mean its the current bound vao.
-            // we need to find a way to check if this vao is bound currently
-
-            // this goes for every type of buffer that we make
-            // vbo, vao, ebo.. etc
-            gl::BindVertexArray(0);
-            gl::DeleteVertexArrays(1, &self.id);
-        }
+        self.ctx.delete_vao(self.id);
     }
 }
 
-pub struct Ebo {
-    id: u32,
-    size: usize,
-}
-
-impl Ebo {
-    pub fn new() -> Self {
-        Self {
-            id: unsafe {
-                let mut id = 0;
-                gl::GenBuffers(1, &mut id);
-                id
-            },
-            size: 0,
+// need to add chunks
+fn gen_indices<T, const CHUNK: usize>(data: &Vec<T>) -> GenBufferArrays<T, CHUNK>
+where
+    T: Copy + PartialEq + std::fmt::Debug,
+{
+    assert!(CHUNK != 0);
+    let mut iv = Vec::<IndexValue<[T; CHUNK]>>::new();
+
+    // step 1: make iv
+    // no dups in iv, makes making the vbo easier
+
+    for chunk in data.array_chunks::<CHUNK>() {
+        // SAFETY: wtf... I just need to copy the [&T; CHUNK] to [T; CHUNK]
+        // because I cant initialize the [T; CHUNK] first
+        // ... not sure what to do here
+        // https://d3m3vilurr.gitbooks.io/the-unsafe-rust-programming-language/content/uninitialized.html
+        // this seems safe because its just plain ol data
+        let mut copy: [T; CHUNK] = unsafe { std::mem::MaybeUninit::uninit().assume_init() };
+        chunk.clone_into(&mut copy);
+        // the index is where it will end up in the vbo, not where it was in data
+        let temp_iv = IndexValue {
+            index: iv.len() as u32,
+            val: copy,
+        };
+        if !iv.contains(&temp_iv) {
+            iv.push(temp_iv);
         }
     }
 
-    pub fn bind(&self) {
-        unsafe {
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id);
-        }
-    }
+    let mut vbo_buffer = Vec::with_capacity(iv.len()); // size of vbo with no dups
+    let mut ebo_buffer = Vec::with_capacity(data.len() / CHUNK); // will be size of vbo
 
-    pub fn set_data(&mut self, data: &Vec<u32>) {
-        self.bind();
-        self.size = std::mem::size_of::<u32>() * data.len();
-        unsafe {
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                self.size as isize,
-                data.as_ptr() as *const _,
-                gl::STATIC_DRAW,
-            )
+    // step 2 fill ebo
+    for c in data.chunks(CHUNK) {
+        if let Some(found) = iv.iter().find(|find| find.val == c) {
+            ebo_buffer.push(found.index);
         }
     }
 
-    // need to add chunks
-    fn gen_indices<T, const CHUNK: usize>(&self, data: &Vec<T>) -> GenBufferArrays<T, CHUNK>
-    where
-        T: Copy + PartialEq + std::fmt::Debug,
-    {
-        assert!(CHUNK != 0);
-        let mut iv = Vec::<IndexValue<[T; CHUNK]>>::new();
-
-        // step 1: make iv
-        // no dups in iv, makes making the vbo easier
-
-        for (i, chunk) in data.array_chunks::<CHUNK>().enumerate() {
-            // SAFETY: wtf... I just need to copy the [&T; CHUNK] to [T; CHUNK]
-            // because I cant initialize the [T; CHUNK] first
-            // ... not sure what to do here
-            // https://d3m3vilurr.gitbooks.io/the-unsafe-rust-programming-language/content/uninitialized.html
-            // this seems safe because its just plain ol data
-            let mut copy: [T; CHUNK] = unsafe { std::mem::MaybeUninit::uninit().assume_init() };
-            chunk.clone_into(&mut copy);
-            let temp_iv = IndexValue {
-                index: i as u32,
-                val: copy,
-            };
-            if !iv.contains(&temp_iv) {
-                println!("{:?}", temp_iv);
-                iv.push(temp_iv);
-            }
-        }
-
-        let mut vbo_buffer = Vec::with_capacity(iv.len()); // size of vbo with no dups
-        let mut ebo_buffer = Vec::with_capacity(data.len() / CHUNK); // will be size of vbo
+    // step 3 copy verts to new vbo_buffer
+    vbo_buffer.extend(iv.iter().map(|iv| iv.val));
 
-        // step 2 fill ebo
-        for c in data.chunks(CHUNK) {
-            if let Some(found) = iv.iter().find(|find| find.val == c) {
-                ebo_buffer.push(found.index);
-            }
-        }
-
-        // step 3 copy verts to new vbo_buffer
-        vbo_buffer.extend(iv.iter().map(|iv| iv.val));
-
-        GenBufferArrays {
-            vbo_buffer,
-            ebo_buffer,
-        }
+    GenBufferArrays {
+        vbo_buffer,
+        ebo_buffer,
     }
 }
 
@@ -303,21 +282,21 @@ struct GenBufferArrays<T, const CHUNK: usize> {
     ebo_buffer: Vec<u32>,
 }
 
-impl Drop for Ebo {
-    fn drop(&mut self) {
-        unsafe {
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
-            gl::DeleteBuffers(1, &self.id)
-        }
-    }
-}
-
+// borrowed for the built in sources, owned for ones loaded at runtime
 pub enum ShaderType {
-    Vertex(&'static str),
-    Fragment(&'static str),
+    Vertex(Cow<'static, str>),
+    Fragment(Cow<'static, str>),
 }
 
 impl ShaderType {
+    pub fn vertex(source: impl Into<Cow<'static, str>>) -> Self {
+        ShaderType::Vertex(source.into())
+    }
+
+    pub fn fragment(source: impl Into<Cow<'static, str>>) -> Self {
+        ShaderType::Fragment(source.into())
+    }
+
     pub fn get_type(&self) -> gl::types::GLuint {
         match self {
             ShaderType::Vertex(_) => gl::VERTEX_SHADER,
@@ -325,7 +304,7 @@ impl ShaderType {
         }
     }
 
-    pub fn get_source(&self) -> &'static str {
+    pub fn get_source(&self) -> &str {
         match self {
             ShaderType::Vertex(s) => s,
             ShaderType::Fragment(s) => s,
@@ -378,22 +357,22 @@ impl ShaderBuilder {
 
         Shader {
             program: self.program,
+            ctx: GlContext::current(),
         }
     }
 }
 
 pub struct Shader {
     program: u32,
+    ctx: Rc<GlContext>,
 }
 
 impl Shader {
     pub fn bind(&self) {
-        unsafe {
-            gl::UseProgram(self.program);
-        }
+        self.ctx.use_program(self.program);
     }
 
-    pub fn set_uniform<T: Uniform>(&mut self, name: &str, mut uniform: T) {
+    pub fn set_uniform<T: Uniform>(&self, name: &str, mut uniform: T) {
         self.bind();
         unsafe {
             let a = CString::new(name).expect("Failed to make CString from Shader set_uniform");
@@ -405,9 +384,7 @@ impl Shader {
 
 impl Drop for Shader {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteProgram(self.program);
-        }
+        self.ctx.delete_program(self.program);
     }
 }
 
@@ -441,6 +418,39 @@ impl Default for Material {
     }
 }
 
+impl Material {
+    // for shapes with their own colors in every vertex, set_color tints them
+    pub fn vertex_colors() -> Self {
+        let mut shader_builder = ShaderBuilder::new();
+        shader_builder.add_shader(shaders::COLOR_VERTEX_2D);
+        shader_builder.add_shader(shaders::COLOR_FRAGMENT_2D);
+
+        let mut shader = shader_builder.build();
+        shader.bind();
+        shader.set_uniform("color", Color::WHITE);
+
+        Self { shader }
+    }
+}
+
+impl Material {
+    // for DrawStream::draw_instanced, set `textured` to 1 and bind a texture to unit 0
+    // to sample it with each instance's uv rect
+    pub fn instanced() -> Self {
+        let mut shader_builder = ShaderBuilder::new();
+        shader_builder.add_shader(shaders::INSTANCED_VERTEX_2D);
+        shader_builder.add_shader(shaders::INSTANCED_FRAGMENT_2D);
+
+        let mut shader = shader_builder.build();
+        shader.bind();
+        shader.set_uniform("color", Color::WHITE);
+        shader.set_uniform("image", 0i32);
+        shader.set_uniform("textured", 0i32);
+
+        Self { shader }
+    }
+}
+
 pub struct DrawStream {
     vao: Vao,
     vbo: Vbo,
@@ -448,17 +458,44 @@ pub struct DrawStream {
 }
 
 impl DrawStream {
-    pub fn draw(&self, mat: &Material) {
+    // the mesh stays where it was uploaded and the transform moves it, so one stream
+    // can be drawn any number of times in different places
+    pub fn draw(&self, mat: &Material, transform: &impl Transform) {
         mat.bind();
+        mat.shader.set_uniform("model", transform.matrix());
         self.vao.bind();
 
-        // 2 because its 2d, each vertex is xy, but this wont be the case if we store color date in each vertex as well
-        // it should be : get_size() / vertex.size()
-        // let size = self.vbo.get_size() as i32 / 2;
-        unsafe {
-            //gl::DrawArrays(gl::TRIANGLES, 0, size);
-            gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, std::ptr::null());
+        self.ebo.draw_elements(gl::TRIANGLES, 0, self.ebo.len());
+    }
+}
+
+impl DrawStream {
+    // every instance in one call, the instances carry their own transform so the model
+    // uniform is only there to move all of them at once
+    pub fn draw_instanced(&self, mat: &Material, instances: &InstanceBuffer) {
+        if instances.is_empty() {
+            return;
         }
+
+        mat.bind();
+        mat.shader.set_uniform("model", Mat3d::identity());
+        self.vao.bind();
+        instances.bind_attributes();
+
+        self.ebo
+            .draw_elements_instanced(gl::TRIANGLES, 0, self.ebo.len(), instances.len());
+        instances.unbind_attributes();
+    }
+
+    // CHUNK is the floats in one vertex so whole vertices get deduplicated and not just positions
+    fn indexed<const CHUNK: usize>(verts: &Vec<f32>, ebo: &mut Ebo) -> Vbo {
+        let GenBufferArrays {
+            ebo_buffer,
+            vbo_buffer,
+        } = gen_indices::<_, CHUNK>(verts);
+        ebo.set_data(&ebo_buffer);
+
+        Vbo::new_bind_buffer(&vbo_buffer.concat())
     }
 }
 
@@ -474,18 +511,55 @@ impl<T: Shape> From<T> for DrawStream {
         let mut ebo = Ebo::new();
 
         // step3: get indice list from verts
-        // let indices = vec![0, 1, 3, 1, 2, 3]; hard coded way
-        let GenBufferArrays {
-            ebo_buffer,
-            vbo_buffer,
-        } = ebo.gen_indices::<_, 2>(&verts);
-        ebo.set_data(&ebo_buffer);
-
         // step4: remove duplicate verts
-        let mut vbo = Vbo::new_bind_buffer(&vbo_buffer);
+        let mut vbo = match s.vertex_floats() {
+            2 => Self::indexed::<2>(&verts, &mut ebo),
+            6 => Self::indexed::<6>(&verts, &mut ebo),
+            n => panic!("DrawStream does not know vertices that are {n} floats long"),
+        };
         s.set_attributes(&mut vbo, &mut vao);
 
         // step5: return
         Self { vbo, vao, ebo }
     }
 }
+
+#[test]
+fn test_gen_indices() {
+    // the square's two triangles share two corners
+    #[rustfmt::skip]
+    let verts = vec![
+        0., 0.,  0., 1.,  1., 0.,
+        1., 1.,  1., 0.,  0., 1.,
+    ];
+
+    let GenBufferArrays {
+        vbo_buffer,
+        ebo_buffer,
+    } = gen_indices::<f32, 2>(&verts);
+
+    assert_eq!(vbo_buffer, vec![[0., 0.], [0., 1.], [1., 0.], [1., 1.]]);
+    assert_eq!(ebo_buffer, vec![0, 1, 2, 3, 2, 1]);
+}
+
+#[test]
+fn test_gen_indices_shared_center() {
+    // a fan around the origin, every triangle reuses the center so later
+    // vertices have to point at their deduplicated spot and not their position in data
+    #[rustfmt::skip]
+    let verts = vec![
+        0., 0.,  1., 0.,  0., 1.,
+        0., 0.,  0., 1.,  -1., 0.,
+    ];
+
+    let GenBufferArrays {
+        vbo_buffer,
+        ebo_buffer,
+    } = gen_indices::<f32, 2>(&verts);
+
+    assert_eq!(vbo_buffer.len(), 4);
+    assert_eq!(ebo_buffer, vec![0, 1, 2, 0, 2, 3]);
+    for (i, index) in ebo_buffer.iter().enumerate() {
+        assert_eq!(vbo_buffer[*index as usize][..], verts[i * 2..i * 2 + 2]);
+    }
+}
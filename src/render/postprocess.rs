@@ -0,0 +1,276 @@
+use std::borrow::Cow;
+
+use crate::math::vector::*;
+
+use super::framebuffer::RenderTarget;
+use super::ogl::{Shader, ShaderBuilder, ShaderType, Uniform, Vao};
+use super::shaders;
+use super::texture::Texture;
+use super::window::Resize;
+
+// a single triangle that covers the whole target, the vertex shader makes the corners itself
+pub struct FullscreenQuad {
+    vao: Vao,
+}
+
+impl FullscreenQuad {
+    pub fn new() -> Self {
+        Self { vao: Vao::new() }
+    }
+
+    pub fn draw(&self, shader: &Shader) {
+        shader.bind();
+        self.vao.bind();
+
+        unsafe {
+            let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+            gl::Disable(gl::DEPTH_TEST);
+
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+
+            if depth_test {
+                gl::Enable(gl::DEPTH_TEST);
+            }
+        }
+    }
+}
+
+impl Default for FullscreenQuad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// one step of the chain, reads input and draws into output
+pub trait PostEffect {
+    fn apply(&mut self, quad: &FullscreenQuad, input: &Texture, output: &RenderTarget);
+}
+
+// a post fragment shader with its own uniforms and any extra textures it reads
+pub struct PostPass {
+    shader: Shader,
+    textures: Vec<Texture>,
+}
+
+impl PostPass {
+    // the fragment shader gets `uv`, `screen` and `resolution`, see shaders::POST_VERTEX
+    pub fn new(fragment: ShaderType) -> Self {
+        let mut builder = ShaderBuilder::new();
+        builder.add_shader(shaders::POST_VERTEX);
+        builder.add_shader(fragment);
+        Self::from_shader(builder.build())
+    }
+
+    // for a fragment shader loaded at runtime, `PostPass::from_source(fs::read_to_string(path)?)`
+    pub fn from_source(fragment: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ShaderType::fragment(fragment))
+    }
+
+    // for when the vertex shader is custom too, it still has to output `uv`
+    pub fn from_shader(mut shader: Shader) -> Self {
+        shader.set_uniform("screen", 0i32);
+        Self {
+            shader,
+            textures: Vec::new(),
+        }
+    }
+
+    pub fn set_uniform<T: Uniform>(&mut self, name: &str, uniform: T) {
+        self.shader.set_uniform(name, uniform);
+    }
+
+    // extra textures go on units 1 and up, in the order they were added
+    pub fn add_texture(&mut self, name: &str, texture: Texture) {
+        self.textures.push(texture);
+        self.shader.set_uniform(name, self.textures.len() as i32);
+    }
+
+    pub fn copy() -> Self {
+        Self::new(shaders::POST_COPY)
+    }
+
+    pub fn grayscale() -> Self {
+        Self::new(shaders::POST_GRAYSCALE)
+    }
+
+    // strength is 0 to 1, radius is how far from the center the darkening ends
+    pub fn vignette(strength: f32, radius: f32) -> Self {
+        let mut pass = Self::new(shaders::POST_VIGNETTE);
+        pass.set_uniform("strength", strength);
+        pass.set_uniform("radius", radius);
+        pass
+    }
+
+    // lut is a strip of size slices that are each size by size, so 256x16 for size 16
+    pub fn color_grade(lut: Texture, size: u32) -> Self {
+        let mut pass = Self::new(shaders::POST_LUT);
+        pass.set_uniform("lut_size", size as f32);
+        pass.add_texture("lut", lut);
+        pass
+    }
+
+    pub fn fxaa() -> Self {
+        Self::new(shaders::POST_FXAA)
+    }
+
+    // scanlines is 0 to 1, curvature around 0.1 gives a slight tube bulge
+    pub fn crt(scanlines: f32, curvature: f32) -> Self {
+        let mut pass = Self::new(shaders::POST_CRT);
+        pass.set_uniform("scanlines", scanlines);
+        pass.set_uniform("curvature", curvature);
+        pass
+    }
+
+    fn draw(&mut self, quad: &FullscreenQuad, input: &Texture, output: &RenderTarget) {
+        let (w, h) = output.size();
+
+        output.bind();
+        input.bind(0);
+        for (i, texture) in self.textures.iter().enumerate() {
+            texture.bind(i as u32 + 1);
+        }
+
+        self.shader
+            .set_uniform("resolution", vec2(w as f32, h as f32));
+        quad.draw(&self.shader);
+        output.unbind();
+    }
+}
+
+impl PostEffect for PostPass {
+    fn apply(&mut self, quad: &FullscreenQuad, input: &Texture, output: &RenderTarget) {
+        self.draw(quad, input, output);
+    }
+}
+
+// bright parts are cut out, blurred at half size and added back on top
+pub struct Bloom {
+    threshold: PostPass,
+    blur: PostPass,
+    combine: PostPass,
+    passes: u32,
+    targets: Option<[RenderTarget; 2]>,
+}
+
+impl Bloom {
+    pub fn new(threshold: f32, intensity: f32) -> Self {
+        let mut threshold_pass = PostPass::new(shaders::POST_BLOOM_THRESHOLD);
+        threshold_pass.set_uniform("threshold", threshold);
+
+        let mut combine = PostPass::new(shaders::POST_BLOOM_COMBINE);
+        combine.set_uniform("intensity", intensity);
+        combine.set_uniform("bloom", 1i32);
+
+        Self {
+            threshold: threshold_pass,
+            blur: PostPass::new(shaders::POST_BLUR),
+            combine,
+            passes: 2,
+            targets: None,
+        }
+    }
+
+    // how many horizontal + vertical blur pairs to run, more is wider and slower
+    pub fn set_blur_passes(&mut self, passes: u32) {
+        self.passes = passes.max(1);
+    }
+}
+
+impl PostEffect for Bloom {
+    fn apply(&mut self, quad: &FullscreenQuad, input: &Texture, output: &RenderTarget) {
+        let (w, h) = input.size();
+        let half = ((w / 2).max(1), (h / 2).max(1));
+
+        let remake = match &self.targets {
+            Some(targets) => targets[0].size() != half,
+            None => true,
+        };
+        if remake {
+            self.targets = Some([
+                RenderTarget::new(half.0, half.1),
+                RenderTarget::new(half.0, half.1),
+            ]);
+        }
+        let [a, b] = self.targets.as_ref().unwrap();
+
+        self.threshold.draw(quad, input, a);
+        for _ in 0..self.passes {
+            self.blur.set_uniform("direction", vec2(1.0, 0.0));
+            self.blur.draw(quad, a.texture(), b);
+            self.blur.set_uniform("direction", vec2(0.0, 1.0));
+            self.blur.draw(quad, b.texture(), a);
+        }
+
+        a.texture().bind(1);
+        self.combine.draw(quad, input, output);
+    }
+}
+
+// draw the scene between begin and end, then every effect runs in order,
+// each one reading what the last one wrote, and the result lands where drawing was going before begin
+pub struct PostProcess {
+    targets: [RenderTarget; 2],
+    effects: Vec<Box<dyn PostEffect>>,
+    quad: FullscreenQuad,
+    copy: PostPass,
+}
+
+impl PostProcess {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            targets: [
+                RenderTarget::new(width, height),
+                RenderTarget::new(width, height),
+            ],
+            effects: Vec::new(),
+            quad: FullscreenQuad::new(),
+            copy: PostPass::copy(),
+        }
+    }
+
+    pub fn add<E: PostEffect + 'static>(&mut self, effect: E) {
+        self.effects.push(Box::new(effect));
+    }
+
+    pub fn clear_effects(&mut self) {
+        self.effects.clear();
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        for target in &mut self.targets {
+            target
+                .resize(width, height)
+                .expect("Failed to resize a post process target");
+        }
+    }
+
+    // same as RenderTarget::follow, for Window::on_resize
+    pub fn follow(&mut self, resize: &Resize) {
+        let (w, h) = resize.framebuffer_size;
+        if w > 0 && h > 0 {
+            self.resize(w as u32, h as u32);
+        }
+    }
+
+    pub fn begin(&self) {
+        self.targets[0].bind();
+    }
+
+    pub fn end(&mut self) {
+        self.targets[0].unbind();
+
+        let mut current = 0;
+        for effect in &mut self.effects {
+            let (input, output) = (&self.targets[current], &self.targets[1 - current]);
+            effect.apply(&self.quad, input.texture(), output);
+            current = 1 - current;
+        }
+
+        // draw straight into whatever was bound before begin, so no bind/unbind here
+        self.targets[current].texture().bind(0);
+        let (w, h) = self.targets[current].size();
+        self.copy
+            .set_uniform("resolution", vec2(w as f32, h as f32));
+        self.quad.draw(&self.copy.shader);
+    }
+}
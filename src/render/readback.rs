@@ -0,0 +1,401 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gl::types::GLsync;
+use image::{DynamicImage, ImageError, ImageResult, RgbaImage};
+
+use super::context::GlContext;
+use super::window::{FrameData, InputEvents, Key, Window};
+
+// anything glReadPixels can read out of
+pub trait PixelSource {
+    fn read_size(&self) -> (u32, u32);
+
+    // bind as the READ_FRAMEBUFFER and pick the buffer to read from
+    fn bind_read(&self);
+
+    // the window's alpha channel is whatever the driver left in it, so it gets thrown away
+    fn keeps_alpha(&self) -> bool;
+}
+
+impl PixelSource for Window {
+    fn read_size(&self) -> (u32, u32) {
+        let (w, h) = self.get_framebuffer_size();
+        (w as u32, h as u32)
+    }
+
+    fn bind_read(&self) {
+        GlContext::current().bind_framebuffer(gl::READ_FRAMEBUFFER, 0);
+        unsafe {
+            gl::ReadBuffer(gl::BACK);
+        }
+    }
+
+    fn keeps_alpha(&self) -> bool {
+        false
+    }
+}
+
+// blocks until the gpu has finished drawing, fine for tests and screenshots but not every frame
+pub fn read_pixels<S: PixelSource + ?Sized>(source: &S) -> RgbaImage {
+    let (w, h) = source.read_size();
+    let mut pixels = vec![0u8; (w * h * 4) as usize];
+
+    unsafe {
+        source.bind_read();
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            w as i32,
+            h as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr().cast(),
+        );
+    }
+    GlContext::current().bind_framebuffer(gl::READ_FRAMEBUFFER, 0);
+
+    into_image(pixels, w, h, source.keeps_alpha())
+}
+
+fn into_image(mut pixels: Vec<u8>, width: u32, height: u32, keep_alpha: bool) -> RgbaImage {
+    // gl gives rows back starting at the bottom, images want them starting at the top
+    flip_rows(&mut pixels, width as usize * 4);
+
+    if !keep_alpha {
+        for alpha in pixels.iter_mut().skip(3).step_by(4) {
+            *alpha = 255;
+        }
+    }
+
+    RgbaImage::from_raw(width, height, pixels).expect("Pixel buffer does not match the image size")
+}
+
+pub fn flip_rows(pixels: &mut [u8], row_bytes: usize) {
+    let rows = pixels.len() / row_bytes;
+    for y in 0..rows / 2 {
+        let (top, bottom) = pixels.split_at_mut((rows - 1 - y) * row_bytes);
+        top[y * row_bytes..(y + 1) * row_bytes].swap_with_slice(&mut bottom[..row_bytes]);
+    }
+}
+
+// the format comes from the file extension, jpeg has no alpha so it gets dropped first
+pub fn save_image<P: AsRef<Path>>(image: &RgbaImage, path: P) -> ImageResult<()> {
+    let path = path.as_ref();
+    let is_jpeg = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| matches!(e.to_lowercase().as_str(), "jpg" | "jpeg"))
+        .unwrap_or(false);
+
+    if is_jpeg {
+        DynamicImage::ImageRgba8(image.clone())
+            .into_rgb8()
+            .save(path)
+    } else {
+        image.save(path)
+    }
+}
+
+// a read that finished on the gpu but couldnt be copied out, that frame is lost and its slot
+// is free again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadbackError {
+    // glMapBufferRange gave back null
+    MapFailed,
+    // the contents got lost while mapped, glUnmapBuffer said so
+    Corrupted,
+}
+
+impl Display for ReadbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadbackError::MapFailed => write!(f, "Failed to map the pixel buffer of a read"),
+            ReadbackError::Corrupted => {
+                write!(f, "The pixel buffer of a read got corrupted while mapped")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReadbackError {}
+
+struct ReadSlot {
+    pbo: u32,
+    fence: Option<GLsync>,
+    size: (u32, u32),
+    keep_alpha: bool,
+    sequence: u64,
+}
+
+// reads pixels into pixel buffer objects so the cpu doesnt wait on the gpu,
+// the image comes out of poll a frame or two after the request
+pub struct AsyncReadback {
+    slots: Vec<ReadSlot>,
+    sequence: u64,
+    ctx: Rc<GlContext>,
+}
+
+impl AsyncReadback {
+    // slots is how many reads can be in flight at once
+    pub fn new(slots: usize) -> Self {
+        assert!(slots != 0);
+
+        let slots = (0..slots)
+            .map(|_| {
+                let mut pbo = 0;
+                unsafe {
+                    gl::GenBuffers(1, &mut pbo);
+                }
+                ReadSlot {
+                    pbo,
+                    fence: None,
+                    size: (0, 0),
+                    keep_alpha: true,
+                    sequence: 0,
+                }
+            })
+            .collect();
+
+        Self {
+            slots,
+            sequence: 0,
+            ctx: GlContext::current(),
+        }
+    }
+
+    // returns false if every slot is still waiting on the gpu
+    pub fn request<S: PixelSource + ?Sized>(&mut self, source: &S) -> bool {
+        let Some(slot) = self.slots.iter_mut().find(|s| s.fence.is_none()) else {
+            return false;
+        };
+
+        let (w, h) = source.read_size();
+        let bytes = (w * h * 4) as isize;
+
+        self.ctx.bind_buffer(gl::PIXEL_PACK_BUFFER, slot.pbo);
+        unsafe {
+            if slot.size != (w, h) {
+                gl::BufferData(
+                    gl::PIXEL_PACK_BUFFER,
+                    bytes,
+                    std::ptr::null(),
+                    gl::STREAM_READ,
+                );
+            }
+
+            source.bind_read();
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            // with a pack buffer bound the last argument is an offset into it, so this returns right away
+            gl::ReadPixels(
+                0,
+                0,
+                w as i32,
+                h as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null_mut(),
+            );
+
+            slot.fence = Some(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0));
+        }
+        self.ctx.bind_framebuffer(gl::READ_FRAMEBUFFER, 0);
+        // left bound every other glReadPixels would write into it
+        self.ctx.bind_buffer(gl::PIXEL_PACK_BUFFER, 0);
+
+        self.sequence += 1;
+        slot.size = (w, h);
+        slot.keep_alpha = source.keeps_alpha();
+        slot.sequence = self.sequence;
+
+        true
+    }
+
+    // gives back the oldest finished read, Ok(None) while the gpu isnt done with it yet or
+    // nothing was requested
+    pub fn poll(&mut self) -> Result<Option<RgbaImage>, ReadbackError> {
+        let Some(slot) = self
+            .slots
+            .iter_mut()
+            .filter(|s| s.fence.is_some())
+            .min_by_key(|s| s.sequence)
+        else {
+            return Ok(None);
+        };
+
+        let Some(fence) = slot.fence else {
+            return Ok(None);
+        };
+        let status = unsafe { gl::ClientWaitSync(fence, 0, 0) };
+        if status != gl::ALREADY_SIGNALED && status != gl::CONDITION_SATISFIED {
+            return Ok(None);
+        }
+
+        let (w, h) = slot.size;
+        let bytes = (w * h * 4) as usize;
+        let mut pixels = vec![0u8; bytes];
+
+        let read = unsafe {
+            gl::DeleteSync(fence);
+            slot.fence = None;
+
+            self.ctx.bind_buffer(gl::PIXEL_PACK_BUFFER, slot.pbo);
+            let mapped =
+                gl::MapBufferRange(gl::PIXEL_PACK_BUFFER, 0, bytes as isize, gl::MAP_READ_BIT);
+            // unmap returns false when the contents got lost while mapped
+            let read = if mapped.is_null() {
+                Err(ReadbackError::MapFailed)
+            } else {
+                std::ptr::copy_nonoverlapping(mapped as *const u8, pixels.as_mut_ptr(), bytes);
+                match gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER) {
+                    gl::TRUE => Ok(()),
+                    _ => Err(ReadbackError::Corrupted),
+                }
+            };
+            self.ctx.bind_buffer(gl::PIXEL_PACK_BUFFER, 0);
+            read
+        };
+
+        // the slot is free again either way, a failed read is an error instead of a black image
+        read?;
+        Ok(Some(into_image(pixels, w, h, slot.keep_alpha)))
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.slots.iter().filter(|s| s.fence.is_some()).count()
+    }
+}
+
+impl Drop for AsyncReadback {
+    fn drop(&mut self) {
+        for slot in &self.slots {
+            if let Some(fence) = slot.fence {
+                unsafe { gl::DeleteSync(fence) }
+            }
+            self.ctx.delete_buffer(slot.pbo);
+        }
+    }
+}
+
+// press the key and the next capture call saves the back buffer to a timestamped png
+pub struct ScreenshotHotkey {
+    key: Key,
+    dir: PathBuf,
+    pending: bool,
+}
+
+impl ScreenshotHotkey {
+    pub fn new<P: Into<PathBuf>>(key: Key, dir: P) -> Self {
+        Self {
+            key,
+            dir: dir.into(),
+            pending: false,
+        }
+    }
+
+    // pass every event from Window::events through here
+    pub fn handle(&mut self, event: &FrameData) {
+        if event.is_key_pressed(self.key) {
+            self.pending = true;
+        }
+    }
+
+    // call after drawing but before swap_buffers, the back buffer is undefined after the swap
+    pub fn capture(&mut self, window: &Window) -> Option<ImageResult<PathBuf>> {
+        if !std::mem::take(&mut self.pending) {
+            return None;
+        }
+
+        let path = self
+            .dir
+            .join(format!("screenshot-{}.png", timestamp(SystemTime::now())));
+        let saved = std::fs::create_dir_all(&self.dir)
+            .map_err(ImageError::IoError)
+            .and_then(|_| save_image(&window.screenshot(), &path))
+            .map(|_| path);
+
+        Some(saved)
+    }
+}
+
+// yyyymmdd-hhmmss-mmm in utc, without pulling in a date crate
+fn timestamp(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs() as i64;
+    let (days, day_secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // days to a civil date, from Howard Hinnant's chrono-compatible date algorithms
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}-{:03}",
+        day_secs / 3600,
+        day_secs / 60 % 60,
+        day_secs % 60,
+        since.subsec_millis()
+    )
+}
+
+#[test]
+fn test_flip_rows() {
+    let mut pixels = vec![1, 1, 2, 2, 3, 3];
+    flip_rows(&mut pixels, 2);
+    assert_eq!(pixels, vec![3, 3, 2, 2, 1, 1]);
+
+    let mut pixels = vec![1, 2, 3, 4];
+    flip_rows(&mut pixels, 2);
+    assert_eq!(pixels, vec![3, 4, 1, 2]);
+}
+
+#[test]
+fn test_timestamp() {
+    use std::time::Duration;
+
+    assert_eq!(timestamp(UNIX_EPOCH), "19700101-000000-000");
+
+    let time = UNIX_EPOCH + Duration::from_millis(1_709_210_096_250);
+    assert_eq!(timestamp(time), "20240229-123456-250");
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn test_async_readback() {
+    use super::golden;
+    use crate::math::color::Color;
+
+    golden::render(4, 2, Color::BLACK, || {
+        let target = super::framebuffer::RenderTarget::new(4, 2);
+        target.bind();
+        unsafe {
+            gl::ClearColor(1., 0., 0., 1.);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        target.unbind();
+
+        let mut readback = AsyncReadback::new(2);
+        assert_eq!(readback.poll(), Ok(None));
+        assert!(readback.request(&target));
+        assert_eq!(readback.in_flight(), 1);
+
+        // the gpu finishes sooner or later, until then it is not an error
+        let image = loop {
+            if let Some(image) = readback.poll().unwrap() {
+                break image;
+            }
+        };
+        assert_eq!(image.dimensions(), (4, 2));
+        assert_eq!(image.get_pixel(3, 1).0, [255, 0, 0, 255]);
+        assert_eq!(readback.in_flight(), 0);
+    });
+}
@@ -0,0 +1,238 @@
+use gl::types::GLsync;
+
+use super::ogl::{BufferUsage, Vbo};
+
+// a vertex buffer for data that is rewritten every frame. the storage is split in three and
+// each frame writes into the next third with unsynchronized mapped writes, a fence per third
+// makes sure the gpu is done drawing from it before it comes around again
+
+const SECTIONS: usize = 3;
+// how long one wait on a fence sleeps at most, in nanoseconds
+const FENCE_WAIT: u64 = 100_000_000;
+// a gpu that is this many waits behind is not coming back
+const MAX_FENCE_WAITS: usize = 50;
+
+// hands out aligned byte ranges inside the current section
+#[derive(Debug, Clone, Copy)]
+struct RingAllocator {
+    section_bytes: usize,
+    section: usize,
+    used: usize,
+}
+
+impl RingAllocator {
+    fn new(section_bytes: usize) -> Self {
+        Self {
+            section_bytes,
+            section: 0,
+            used: 0,
+        }
+    }
+
+    // byte offset from the start of the whole buffer, none when the section is full
+    fn alloc(&mut self, bytes: usize, align: usize) -> Option<usize> {
+        let base = self.section * self.section_bytes;
+        let start = (base + self.used).next_multiple_of(align.max(1));
+        if start + bytes > base + self.section_bytes {
+            return None;
+        }
+
+        self.used = start + bytes - base;
+        Some(start)
+    }
+
+    fn advance(&mut self) {
+        self.section = (self.section + 1) % SECTIONS;
+        self.used = 0;
+    }
+}
+
+pub struct RingBuffer {
+    vbo: Vbo<u8>,
+    ring: RingAllocator,
+    fences: [GLsync; SECTIONS],
+}
+
+impl RingBuffer {
+    // bytes_per_frame is a starting guess, writes grow it when a frame needs more
+    pub fn new(bytes_per_frame: usize) -> Self {
+        let mut vbo = Vbo::with_usage(BufferUsage::Stream);
+        vbo.reserve(bytes_per_frame.max(1) * SECTIONS);
+
+        Self {
+            vbo,
+            ring: RingAllocator::new(bytes_per_frame.max(1)),
+            fences: [std::ptr::null(); SECTIONS],
+        }
+    }
+
+    // for pointing vao attributes at, the buffer id never changes even when it grows
+    pub fn vbo(&self) -> &Vbo<u8> {
+        &self.vbo
+    }
+
+    pub fn bytes_per_frame(&self) -> usize {
+        self.ring.section_bytes
+    }
+
+    // moves on to the next section, waiting until the gpu is done with it if it has to
+    pub fn begin_frame(&mut self) {
+        self.ring.advance();
+
+        let fence = std::mem::replace(&mut self.fences[self.ring.section], std::ptr::null());
+        if !fence.is_null() {
+            wait_for(fence);
+            unsafe { gl::DeleteSync(fence) }
+        }
+    }
+
+    // after the last draw that reads from this frame's section
+    pub fn end_frame(&mut self) {
+        let fence = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+        let old = std::mem::replace(&mut self.fences[self.ring.section], fence);
+        if !old.is_null() {
+            unsafe { gl::DeleteSync(old) }
+        }
+    }
+
+    // copies data in and returns where it starts counted in strides from the start of the
+    // buffer, so with stride as the vertex size it is the first vertex for DrawArrays
+    pub fn write<T: Copy>(&mut self, data: &[T], stride: usize) -> usize {
+        let bytes = std::mem::size_of_val(data);
+        let offset = match self.ring.alloc(bytes, stride) {
+            Some(offset) => offset,
+            None => {
+                self.grow(bytes + stride);
+                self.ring.alloc(bytes, stride).unwrap()
+            }
+        };
+
+        self.vbo.bind();
+        unsafe {
+            let ptr = gl::MapBufferRange(
+                gl::ARRAY_BUFFER,
+                offset as isize,
+                bytes as isize,
+                gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_RANGE_BIT | gl::MAP_UNSYNCHRONIZED_BIT,
+            );
+            assert!(!ptr.is_null(), "Could not map the ring buffer");
+            std::ptr::copy_nonoverlapping(data.as_ptr().cast::<u8>(), ptr.cast::<u8>(), bytes);
+            gl::UnmapBuffer(gl::ARRAY_BUFFER);
+        }
+
+        offset / stride.max(1)
+    }
+
+    // new storage with room for bytes more this frame. draws already sent keep the old
+    // storage alive so nothing has to wait, and the old fences mean nothing anymore.
+    // what this frame wrote so far is copied to the same place in the new storage, and
+    // the frame carries on in a first section big enough to cover it, so the offsets
+    // write already gave out still point at the right data
+    fn grow(&mut self, bytes: usize) {
+        let base = self.ring.section * self.ring.section_bytes;
+        let written = base + self.ring.used;
+        let section_bytes = (written + bytes)
+            .max(self.ring.section_bytes * 2)
+            .next_power_of_two();
+
+        // the old storage is gone once the new one is made, so it goes through another buffer
+        let mut kept = None;
+        if self.ring.used > 0 {
+            let mut copy = Vbo::with_usage(BufferUsage::Stream);
+            copy.reserve(self.ring.used);
+            copy.copy_from(&self.vbo, base..written, 0);
+            kept = Some(copy);
+        }
+        self.vbo.reserve(section_bytes * SECTIONS);
+        if let Some(kept) = kept {
+            self.vbo.copy_from(&kept, 0..self.ring.used, base);
+        }
+
+        for fence in &mut self.fences {
+            if !fence.is_null() {
+                unsafe { gl::DeleteSync(*fence) }
+            }
+            *fence = std::ptr::null();
+        }
+
+        self.ring = RingAllocator::new(section_bytes);
+        self.ring.used = written;
+    }
+}
+
+// the driver sleeps inside ClientWaitSync until the fence or the timeout, only the first
+// try has to flush to make sure the fence gets to the gpu at all
+fn wait_for(fence: GLsync) {
+    let mut flags = gl::SYNC_FLUSH_COMMANDS_BIT;
+    for _ in 0..MAX_FENCE_WAITS {
+        match unsafe { gl::ClientWaitSync(fence, flags, FENCE_WAIT) } {
+            gl::TIMEOUT_EXPIRED => flags = 0,
+            gl::WAIT_FAILED => panic!("Waiting on a ring buffer fence failed"),
+            _ => return,
+        }
+    }
+    panic!(
+        "The gpu did not finish a frame in {} seconds",
+        MAX_FENCE_WAITS as u64 * FENCE_WAIT / 1_000_000_000
+    );
+}
+
+impl Drop for RingBuffer {
+    fn drop(&mut self) {
+        for fence in self.fences {
+            if !fence.is_null() {
+                unsafe { gl::DeleteSync(fence) }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_ring_allocator() {
+    let mut ring = RingAllocator::new(100);
+
+    assert_eq!(ring.alloc(30, 1), Some(0));
+    // lined up to the stride so the offset divides into a vertex index
+    assert_eq!(ring.alloc(36, 36), Some(36));
+    assert_eq!(ring.alloc(36, 36), None);
+    assert_eq!(ring.alloc(28, 4), Some(72));
+
+    // each frame gets the next third, and it wraps around after the last one
+    ring.advance();
+    assert_eq!(ring.alloc(10, 1), Some(100));
+    ring.advance();
+    assert_eq!(ring.alloc(100, 1), Some(200));
+    assert_eq!(ring.alloc(1, 1), None);
+    ring.advance();
+    assert_eq!(ring.alloc(36, 36), Some(0));
+    // the section starts at 100 so the next multiple of 36 in it is 108
+    ring.advance();
+    assert_eq!(ring.alloc(36, 36), Some(108));
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn test_grow_keeps_frame() {
+    use crate::math::color::Color;
+
+    super::golden::render(4, 4, Color::BLACK, || {
+        let mut ring = RingBuffer::new(16);
+        ring.begin_frame();
+        let first = ring.write(&[1u32, 2, 3], 4);
+        // too big for the section, everything moves into new storage
+        let second = ring.write(&[7u32; 8], 4);
+        assert!(ring.bytes_per_frame() > 16);
+
+        let read = |at: usize, len: usize| ring.vbo().read_back_range(at * 4..(at + len) * 4);
+        assert_eq!(read(first, 3), bytemuck::cast_slice::<u32, u8>(&[1, 2, 3]));
+        assert_eq!(read(second, 8), bytemuck::cast_slice::<u32, u8>(&[7; 8]));
+        ring.end_frame();
+
+        // and the frames after it wait on their fences like before
+        for _ in 0..4 {
+            ring.begin_frame();
+            ring.write(&[0u32; 4], 4);
+            ring.end_frame();
+        }
+    });
+}
@@ -0,0 +1,394 @@
+use std::cell::Cell;
+
+use crate::math::matrix::Mat3d;
+use crate::math::transform::{Transform, Transform3D};
+
+use super::ogl::{DrawStream, Material};
+
+// a tree of nodes that place each other, a child's transform is relative to its parent so
+// moving a body moves its arms along with it. world matrices are only worked out again
+// when something above them changed and somebody asks
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId {
+    index: usize,
+    // slots get used again after a remove, this tells the new node from the old one
+    generation: u32,
+}
+
+pub struct Drawable {
+    pub stream: DrawStream,
+    pub material: Material,
+}
+
+pub struct Node {
+    pub name: String,
+    // hides the node and everything under it
+    pub visible: bool,
+    pub drawable: Option<Drawable>,
+    local: Transform3D,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world: Cell<Mat3d>,
+    dirty: Cell<bool>,
+}
+
+impl Node {
+    pub fn local(&self) -> &Transform3D {
+        &self.local
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+struct Slot {
+    generation: u32,
+    node: Option<Node>,
+}
+
+#[derive(Default)]
+pub struct Scene {
+    // a removed node frees its slot for the next add, the generation goes up when it does
+    // so old ids never point at somebody else
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    roots: Vec<NodeId>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // a new empty node at the origin of its parent, or at the top of the scene without one
+    pub fn add(&mut self, name: &str, parent: Option<NodeId>) -> NodeId {
+        // checked before anything changes so a bad parent leaves no half added node behind
+        if let Some(parent) = parent {
+            assert!(self.contains(parent), "Parent was removed from the scene");
+        }
+
+        let node = Node {
+            name: name.to_string(),
+            visible: true,
+            drawable: None,
+            local: Transform3D::default(),
+            parent: None,
+            children: Vec::new(),
+            world: Cell::new(Mat3d::identity()),
+            dirty: Cell::new(true),
+        };
+        let id = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.node = Some(node);
+                NodeId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    node: Some(node),
+                });
+                NodeId {
+                    index: self.slots.len() - 1,
+                    generation: 0,
+                }
+            }
+        };
+
+        self.attach(id, parent);
+        id
+    }
+
+    // takes the node and everything under it out of the scene
+    pub fn remove(&mut self, id: NodeId) {
+        self.detach(id);
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let slot = &mut self.slots[id.index];
+            if let Some(node) = slot.node.take() {
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free.push(id.index);
+                stack.extend(node.children);
+            }
+        }
+    }
+
+    // moves the node under another parent, its local transform stays the same so it will
+    // jump to wherever that puts it
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+        if let Some(parent) = parent {
+            assert!(
+                !self.walk_from(id).any(|n| n == parent),
+                "A node can not be parented to itself or one of its children"
+            );
+        }
+
+        self.detach(id);
+        self.attach(id, parent);
+    }
+
+    fn attach(&mut self, id: NodeId, parent: Option<NodeId>) {
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+        self.node_mut(id).parent = parent;
+        self.mark_dirty(id);
+    }
+
+    fn detach(&mut self, id: NodeId) {
+        let siblings = match self.node(id).parent {
+            Some(parent) => &mut self.node_mut(parent).children,
+            None => &mut self.roots,
+        };
+        siblings.retain(|n| *n != id);
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.slot(id).is_some()
+    }
+
+    fn slot(&self, id: NodeId) -> Option<&Slot> {
+        self.slots
+            .get(id.index)
+            .filter(|s| s.generation == id.generation && s.node.is_some())
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        self.slot(id)
+            .and_then(|s| s.node.as_ref())
+            .expect("Node was removed from the scene")
+    }
+
+    // the transform is left out on purpose, change it with set_local so the world
+    // matrices know to update
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.slots
+            .get_mut(id.index)
+            .filter(|s| s.generation == id.generation)
+            .and_then(|s| s.node.as_mut())
+            .expect("Node was removed from the scene")
+    }
+
+    pub fn set_local(&mut self, id: NodeId, local: impl Into<Transform3D>) {
+        self.node_mut(id).local = local.into();
+        self.mark_dirty(id);
+    }
+
+    // changes the transform in place, `scene.update_local(arm, |t| t.rotation[Z] += 0.1)`
+    pub fn update_local(&mut self, id: NodeId, f: impl FnOnce(&mut Transform3D)) {
+        f(&mut self.node_mut(id).local);
+        self.mark_dirty(id);
+    }
+
+    pub fn set_drawable(&mut self, id: NodeId, stream: DrawStream, material: Material) {
+        self.node_mut(id).drawable = Some(Drawable { stream, material });
+    }
+
+    // everything under a node moves with it, so they all have to be worked out again
+    fn mark_dirty(&self, id: NodeId) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = self.node(id);
+            // a node only gets clean after its parents did, so one that is already dirty
+            // has nothing clean under it
+            if !node.dirty.replace(true) {
+                stack.extend(&node.children);
+            }
+        }
+    }
+
+    // parent world * local, only recalculated up the chain that changed
+    pub fn world(&self, id: NodeId) -> Mat3d {
+        let node = self.node(id);
+        if node.dirty.get() {
+            let parent = node.parent.map_or(Mat3d::identity(), |p| self.world(p));
+            node.world.set(parent * node.local.matrix());
+            node.dirty.set(false);
+        }
+        node.world.get()
+    }
+
+    // false when it or anything above it is hidden
+    pub fn is_visible(&self, id: NodeId) -> bool {
+        let mut next = Some(id);
+        while let Some(id) = next {
+            let node = self.node(id);
+            if !node.visible {
+                return false;
+            }
+            next = node.parent;
+        }
+        true
+    }
+
+    // the first node with the name, in walk order
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.walk().find(|id| self.node(*id).name == name)
+    }
+
+    // names separated by slashes from a top level node down, `"player/body/arm"`
+    pub fn find_path(&self, path: &str) -> Option<NodeId> {
+        let mut siblings = &self.roots;
+        let mut found = None;
+        for name in path.split('/').filter(|n| !n.is_empty()) {
+            let id = *siblings.iter().find(|id| self.node(**id).name == name)?;
+            siblings = &self.node(id).children;
+            found = Some(id);
+        }
+        found
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    // every node, parents before their children
+    pub fn walk(&self) -> Walk<'_> {
+        Walk {
+            scene: self,
+            stack: self.roots.iter().rev().copied().collect(),
+        }
+    }
+
+    // the node and everything under it, parents first
+    pub fn walk_from(&self, id: NodeId) -> Walk<'_> {
+        Walk {
+            scene: self,
+            stack: vec![id],
+        }
+    }
+
+    // draws every visible node that has something to draw, hidden nodes skip their children
+    pub fn draw(&self) {
+        let mut stack: Vec<NodeId> = self.roots.iter().rev().copied().collect();
+        while let Some(id) = stack.pop() {
+            let node = self.node(id);
+            if !node.visible {
+                continue;
+            }
+            if let Some(drawable) = &node.drawable {
+                drawable.stream.draw(&drawable.material, &self.world(id));
+            }
+            stack.extend(node.children.iter().rev());
+        }
+    }
+}
+
+pub struct Walk<'a> {
+    scene: &'a Scene,
+    stack: Vec<NodeId>,
+}
+
+impl Iterator for Walk<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.stack.pop()?;
+        self.stack.extend(self.scene.node(id).children.iter().rev());
+        Some(id)
+    }
+}
+
+#[test]
+fn test_hierarchy() {
+    use crate::math::transform::Transform2D;
+    use crate::math::vector::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    let mut scene = Scene::new();
+    let body = scene.add("body", None);
+    let arm = scene.add("arm", Some(body));
+    let hand = scene.add("hand", Some(arm));
+
+    scene.set_local(body, Transform2D::new(vec2(10., 0.)));
+    // the arm hangs off the body's right side and the hand is at the end of it
+    scene.set_local(arm, Transform2D::new(vec2(1., 0.)));
+    scene.set_local(hand, Transform2D::new(vec2(2., 0.)));
+
+    let at = |scene: &Scene, id| {
+        let p = scene.world(id).transform_point(vec3(0., 0., 0.));
+        (p[X], p[Y])
+    };
+    let near = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5;
+    assert!(near(at(&scene, hand), (13., 0.)));
+
+    // swinging the arm up takes the hand with it but leaves the body
+    scene.update_local(arm, |t| t.rotation[Z] = FRAC_PI_2);
+    assert!(scene.node(hand).dirty.get());
+    assert!(!scene.node(body).dirty.get());
+    assert!(near(at(&scene, hand), (11., 2.)));
+    assert!(near(at(&scene, body), (10., 0.)));
+
+    // moving the body after that still reaches the hand
+    scene.update_local(body, |t| t.position[Y] = 5.);
+    assert!(near(at(&scene, hand), (11., 7.)));
+
+    // moved onto a new parent it keeps its local transform
+    let other = scene.add("other", None);
+    scene.set_parent(hand, Some(other));
+    assert!(near(at(&scene, hand), (2., 0.)));
+    assert_eq!(scene.node(arm).children(), &[]);
+}
+
+#[test]
+fn test_find_and_walk() {
+    let mut scene = Scene::new();
+    let player = scene.add("player", None);
+    let body = scene.add("body", Some(player));
+    let left = scene.add("arm", Some(body));
+    let right = scene.add("arm", Some(body));
+    let tree = scene.add("tree", None);
+
+    assert_eq!(
+        scene.walk().collect::<Vec<_>>(),
+        vec![player, body, left, right, tree]
+    );
+    assert_eq!(scene.walk_from(body).count(), 3);
+
+    assert_eq!(scene.find("arm"), Some(left));
+    assert_eq!(scene.find("nothing"), None);
+    assert_eq!(scene.find_path("player/body/arm"), Some(left));
+    assert_eq!(scene.find_path("body/arm"), None);
+
+    scene.node_mut(body).visible = false;
+    assert!(scene.is_visible(player));
+    assert!(!scene.is_visible(right));
+
+    scene.remove(body);
+    assert!(!scene.contains(left));
+    assert_eq!(scene.walk().collect::<Vec<_>>(), vec![player, tree]);
+}
+
+#[test]
+fn test_remove_and_reuse() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut scene = Scene::new();
+    let player = scene.add("player", None);
+    let sword = scene.add("sword", Some(player));
+    scene.remove(player);
+
+    // a removed parent is refused before the node goes in
+    let added = catch_unwind(AssertUnwindSafe(|| scene.add("shield", Some(sword))));
+    assert!(added.is_err());
+    assert_eq!(scene.walk().count(), 0);
+
+    // new nodes take the freed slots, the old ids dont reach them
+    let tree = scene.add("tree", None);
+    let rock = scene.add("rock", None);
+    assert_eq!(scene.slots.len(), 2);
+    assert!(scene.contains(tree) && scene.contains(rock));
+    assert!(!scene.contains(player) && !scene.contains(sword));
+    assert_ne!(tree, sword);
+    assert_eq!(scene.walk().collect::<Vec<_>>(), vec![tree, rock]);
+}
@@ -1,7 +1,9 @@
+use std::borrow::Cow;
+
 use crate::render::ogl::ShaderType;
 
-pub const BASIC_VERTEX_2D: ShaderType = ShaderType::Vertex(VERTEX_SOURCE);
-pub const BASIC_FRAGMENT_2D: ShaderType = ShaderType::Fragment(FRAGMENT_SOURCE);
+pub const BASIC_VERTEX_2D: ShaderType = ShaderType::Vertex(Cow::Borrowed(VERTEX_SOURCE));
+pub const BASIC_FRAGMENT_2D: ShaderType = ShaderType::Fragment(Cow::Borrowed(FRAGMENT_SOURCE));
 
 const VERTEX_SOURCE: &str = r"
 #version 330 core
@@ -9,13 +11,14 @@ const VERTEX_SOURCE: &str = r"
 layout (location = 0) in vec2 aPos;
 
 uniform vec3 color;
+uniform mat4 model;
 
 out vec3 out_color;
 
 void main()
 {
     out_color = vec3(color.r, color.g, color.b);
-    gl_Position = vec4(aPos.x, aPos.y, 0.0, 1.0);
+    gl_Position = model * vec4(aPos.x, aPos.y, 0.0, 1.0);
 }";
 
 const FRAGMENT_SOURCE: &str = r"
@@ -28,3 +31,365 @@ void main()
 {
     FragColor = vec4(out_color.r, out_color.g, out_color.b, 1);
 }";
+
+// same as the basic shaders but the color comes from every vertex, the uniform tints it
+pub const COLOR_VERTEX_2D: ShaderType = ShaderType::Vertex(Cow::Borrowed(COLOR_VERTEX_SOURCE));
+pub const COLOR_FRAGMENT_2D: ShaderType =
+    ShaderType::Fragment(Cow::Borrowed(COLOR_FRAGMENT_SOURCE));
+
+const COLOR_VERTEX_SOURCE: &str = r"
+#version 330 core
+
+layout (location = 0) in vec2 aPos;
+layout (location = 1) in vec4 aColor;
+
+uniform vec3 color;
+uniform mat4 model;
+
+out vec4 out_color;
+
+void main()
+{
+    out_color = aColor * vec4(color, 1.0);
+    gl_Position = model * vec4(aPos.x, aPos.y, 0.0, 1.0);
+}";
+
+const COLOR_FRAGMENT_SOURCE: &str = r"
+#version 330 core
+
+in vec4 out_color;
+out vec4 FragColor;
+
+void main()
+{
+    FragColor = out_color;
+}";
+
+// for DrawStream::draw_instanced, every instance brings its own model matrix, color and
+// uv rect. the mesh positions from 0 to 1 pick where in the uv rect to sample
+pub const INSTANCED_VERTEX_2D: ShaderType =
+    ShaderType::Vertex(Cow::Borrowed(INSTANCED_VERTEX_SOURCE));
+pub const INSTANCED_FRAGMENT_2D: ShaderType =
+    ShaderType::Fragment(Cow::Borrowed(INSTANCED_FRAGMENT_SOURCE));
+
+const INSTANCED_VERTEX_SOURCE: &str = r"
+#version 330 core
+
+layout (location = 0) in vec2 aPos;
+layout (location = 8) in mat4 aModel;
+layout (location = 12) in vec4 aColor;
+layout (location = 13) in vec4 aUvRect;
+
+uniform vec3 color;
+uniform mat4 model;
+
+out vec4 out_color;
+out vec2 uv;
+
+void main()
+{
+    out_color = aColor * vec4(color, 1.0);
+    uv = mix(aUvRect.xy, aUvRect.zw, aPos);
+    gl_Position = model * aModel * vec4(aPos.x, aPos.y, 0.0, 1.0);
+}";
+
+// without a texture the instances are just their color
+const INSTANCED_FRAGMENT_SOURCE: &str = r"
+#version 330 core
+
+in vec4 out_color;
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D image;
+uniform bool textured;
+
+void main()
+{
+    vec4 texel = textured ? texture(image, uv) : vec4(1.0);
+    FragColor = texel * out_color;
+}";
+
+// *********************
+//   Post Processing
+// *********************
+// every post fragment shader gets `uv`, the input image in `screen` on texture unit 0
+// and its size in pixels in `resolution`
+
+pub const POST_VERTEX: ShaderType = ShaderType::Vertex(Cow::Borrowed(POST_VERTEX_SOURCE));
+pub const POST_COPY: ShaderType = ShaderType::Fragment(Cow::Borrowed(POST_COPY_SOURCE));
+pub const POST_GRAYSCALE: ShaderType = ShaderType::Fragment(Cow::Borrowed(POST_GRAYSCALE_SOURCE));
+pub const POST_VIGNETTE: ShaderType = ShaderType::Fragment(Cow::Borrowed(POST_VIGNETTE_SOURCE));
+pub const POST_BLOOM_THRESHOLD: ShaderType =
+    ShaderType::Fragment(Cow::Borrowed(POST_BLOOM_THRESHOLD_SOURCE));
+pub const POST_BLUR: ShaderType = ShaderType::Fragment(Cow::Borrowed(POST_BLUR_SOURCE));
+pub const POST_BLOOM_COMBINE: ShaderType =
+    ShaderType::Fragment(Cow::Borrowed(POST_BLOOM_COMBINE_SOURCE));
+pub const POST_LUT: ShaderType = ShaderType::Fragment(Cow::Borrowed(POST_LUT_SOURCE));
+pub const POST_FXAA: ShaderType = ShaderType::Fragment(Cow::Borrowed(POST_FXAA_SOURCE));
+pub const POST_CRT: ShaderType = ShaderType::Fragment(Cow::Borrowed(POST_CRT_SOURCE));
+
+// one triangle that covers the screen, no vertex buffer needed
+const POST_VERTEX_SOURCE: &str = r"
+#version 330 core
+
+out vec2 uv;
+
+void main()
+{
+    vec2 pos = vec2(float((gl_VertexID << 1) & 2), float(gl_VertexID & 2));
+    uv = pos;
+    gl_Position = vec4(pos * 2.0 - 1.0, 0.0, 1.0);
+}";
+
+const POST_COPY_SOURCE: &str = r"
+#version 330 core
+
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+
+void main()
+{
+    FragColor = texture(screen, uv);
+}";
+
+const POST_GRAYSCALE_SOURCE: &str = r"
+#version 330 core
+
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+
+void main()
+{
+    vec4 color = texture(screen, uv);
+    float luma = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    FragColor = vec4(vec3(luma), color.a);
+}";
+
+const POST_VIGNETTE_SOURCE: &str = r"
+#version 330 core
+
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+uniform float strength;
+uniform float radius;
+
+void main()
+{
+    vec4 color = texture(screen, uv);
+    float dist = distance(uv, vec2(0.5));
+    float shade = smoothstep(radius, radius - 0.45, dist);
+    FragColor = vec4(color.rgb * mix(1.0, shade, strength), color.a);
+}";
+
+const POST_BLOOM_THRESHOLD_SOURCE: &str = r"
+#version 330 core
+
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+uniform float threshold;
+
+void main()
+{
+    vec3 color = texture(screen, uv).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    float amount = max(brightness - threshold, 0.0) / max(brightness, 0.0001);
+    FragColor = vec4(color * amount, 1.0);
+}";
+
+// 9 tap gaussian, run once with direction (1, 0) and once with (0, 1)
+const POST_BLUR_SOURCE: &str = r"
+#version 330 core
+
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+uniform vec2 resolution;
+uniform vec2 direction;
+
+const float weights[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main()
+{
+    vec2 step = direction / resolution;
+    vec3 color = texture(screen, uv).rgb * weights[0];
+    for (int i = 1; i < 5; i++) {
+        color += texture(screen, uv + step * float(i)).rgb * weights[i];
+        color += texture(screen, uv - step * float(i)).rgb * weights[i];
+    }
+    FragColor = vec4(color, 1.0);
+}";
+
+const POST_BLOOM_COMBINE_SOURCE: &str = r"
+#version 330 core
+
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+uniform sampler2D bloom;
+uniform float intensity;
+
+void main()
+{
+    vec4 color = texture(screen, uv);
+    FragColor = vec4(color.rgb + texture(bloom, uv).rgb * intensity, color.a);
+}";
+
+// the lut is a strip of lut_size slices, each lut_size by lut_size, red goes across
+// a slice, green goes down and blue picks the slice
+const POST_LUT_SOURCE: &str = r"
+#version 330 core
+
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+uniform sampler2D lut;
+uniform float lut_size;
+
+vec3 sample_slice(vec3 color, float slice)
+{
+    float width = lut_size * lut_size;
+    vec2 texel = vec2(1.0 / width, 1.0 / lut_size);
+    vec2 pos = vec2(
+        (slice * lut_size + color.r * (lut_size - 1.0) + 0.5) * texel.x,
+        (color.g * (lut_size - 1.0) + 0.5) * texel.y
+    );
+    return texture(lut, pos).rgb;
+}
+
+void main()
+{
+    vec4 color = clamp(texture(screen, uv), 0.0, 1.0);
+    float blue = color.b * (lut_size - 1.0);
+    float slice = floor(blue);
+    vec3 low = sample_slice(color.rgb, slice);
+    vec3 high = sample_slice(color.rgb, min(slice + 1.0, lut_size - 1.0));
+    FragColor = vec4(mix(low, high, blue - slice), color.a);
+}";
+
+const POST_FXAA_SOURCE: &str = r"
+#version 330 core
+
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+uniform vec2 resolution;
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+
+void main()
+{
+    vec2 inv = 1.0 / resolution;
+    vec3 to_luma = vec3(0.299, 0.587, 0.114);
+
+    float nw = dot(texture(screen, uv + vec2(-1.0, -1.0) * inv).rgb, to_luma);
+    float ne = dot(texture(screen, uv + vec2(1.0, -1.0) * inv).rgb, to_luma);
+    float sw = dot(texture(screen, uv + vec2(-1.0, 1.0) * inv).rgb, to_luma);
+    float se = dot(texture(screen, uv + vec2(1.0, 1.0) * inv).rgb, to_luma);
+    vec4 center = texture(screen, uv);
+    float m = dot(center.rgb, to_luma);
+
+    float luma_min = min(m, min(min(nw, ne), min(sw, se)));
+    float luma_max = max(m, max(max(nw, ne), max(sw, se)));
+
+    vec2 dir = vec2(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+    float reduce = max((nw + ne + sw + se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * inv;
+
+    vec3 a = 0.5 * (
+        texture(screen, uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+        texture(screen, uv + dir * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 b = a * 0.5 + 0.25 * (
+        texture(screen, uv - dir * 0.5).rgb +
+        texture(screen, uv + dir * 0.5).rgb
+    );
+    float luma_b = dot(b, to_luma);
+
+    FragColor = vec4((luma_b < luma_min || luma_b > luma_max) ? a : b, center.a);
+}";
+
+const POST_CRT_SOURCE: &str = r"
+#version 330 core
+
+in vec2 uv;
+out vec4 FragColor;
+
+uniform sampler2D screen;
+uniform vec2 resolution;
+uniform float scanlines;
+uniform float curvature;
+
+void main()
+{
+    vec2 centered = uv * 2.0 - 1.0;
+    centered *= 1.0 + curvature * dot(centered.yx, centered.yx);
+    vec2 curved = centered * 0.5 + 0.5;
+
+    if (curved.x < 0.0 || curved.x > 1.0 || curved.y < 0.0 || curved.y > 1.0) {
+        FragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec3 color = texture(screen, curved).rgb;
+    float line = 0.5 + 0.5 * sin(curved.y * resolution.y * 3.14159265);
+    color *= 1.0 - scanlines * (1.0 - line);
+    FragColor = vec4(color, 1.0);
+}";
+
+// *********************
+//   Sprites
+// *********************
+
+pub const SPRITE_VERTEX: ShaderType = ShaderType::Vertex(Cow::Borrowed(SPRITE_VERTEX_SOURCE));
+pub const SPRITE_FRAGMENT: ShaderType = ShaderType::Fragment(Cow::Borrowed(SPRITE_FRAGMENT_SOURCE));
+
+// positions are in pixels from the top left of the screen
+const SPRITE_VERTEX_SOURCE: &str = r"
+#version 330 core
+
+layout (location = 0) in vec3 aPos;
+layout (location = 1) in vec2 aUv;
+layout (location = 2) in vec4 aColor;
+
+uniform vec2 screen_size;
+
+out vec2 uv;
+out vec4 tint;
+
+void main()
+{
+    uv = aUv;
+    tint = aColor;
+    vec2 ndc = vec2(aPos.x / screen_size.x * 2.0 - 1.0, 1.0 - aPos.y / screen_size.y * 2.0);
+    gl_Position = vec4(ndc, aPos.z * 2.0 - 1.0, 1.0);
+}";
+
+const SPRITE_FRAGMENT_SOURCE: &str = r"
+#version 330 core
+
+in vec2 uv;
+in vec4 tint;
+out vec4 FragColor;
+
+uniform sampler2D sprite;
+
+void main()
+{
+    FragColor = texture(sprite, uv) * tint;
+}";
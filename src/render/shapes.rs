@@ -1,37 +1,91 @@
+use std::f32::consts::TAU;
+
+use crate::math::color::Color;
 use crate::math::vector::{Vec2, *};
 use crate::render::ogl::Vbo;
-use super::ogl::{Material, Vao, VertexPosInfo2D};
+use super::ogl::{Material, Vao, VertexColorInfo, VertexPosInfo2D};
+
+pub mod gradient;
+pub mod path;
+pub mod polygon;
+pub mod stroke;
+
+pub use gradient::{Gradient, GradientFill};
+pub use path::{FillRule, Path};
+pub use polygon::{Polygon, PolygonError};
+pub use stroke::{Dash, LineCap, LineJoin, Outline, Stroke, StrokeStyle};
 
 pub trait Shape {
     fn get_arrays(&self) -> Vec<f32>;
 
     fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao);
+
+    // how many floats each vertex in get_arrays takes, xy first and then whatever
+    // set_attributes says comes after it
+    fn vertex_floats(&self) -> usize {
+        2
+    }
 }
 
 pub struct Square {
     pos: Vec2,
     size: Vec2,
+    colors: Option<[Color; 4]>,
 }
 
 impl Square {
     pub fn new(pos: Vec2, size: Vec2) -> Self {
-        Self { pos, size }
+        Self { pos, size, colors: None }
+    }
+
+    // going around from pos: bottom left, bottom right, top right, top left.
+    // draw it with Material::vertex_colors
+    pub fn with_corner_colors(mut self, colors: [Color; 4]) -> Self {
+        self.colors = Some(colors);
+        self
     }
 }
 
 pub struct Circle {
     pub pos: Vec2,
     pub radius: f32,
+    pub segments: usize,
 }
 
 impl Circle {
+    // the segments fit the default camera, call fit_to_screen when it is drawn bigger or
+    // smaller than that
     pub fn new(pos: Vec2, radius: f32) -> Self {
-        Self { pos, radius }
+        Self {
+            pos,
+            radius,
+            segments: MIN_SEGMENTS,
+        }
+        .fit_to_screen(DEFAULT_PIXELS_PER_UNIT)
     }
 
+    // the number of segments for round things with no radius to go by, like stroke joins
     pub const fn resolution() -> usize {
         16
     }
+
+    pub fn with_segments(mut self, segments: usize) -> Self {
+        self.segments = segments.max(MIN_SEGMENTS);
+        self
+    }
+
+    // how many segments a circle this many pixels across the radius needs so the rim
+    // never cuts more than a quarter pixel inside the real circle
+    pub fn segments_for(radius_in_pixels: f32) -> usize {
+        segments_for(radius_in_pixels)
+    }
+
+    // picks the segments from how big the circle ends up on screen, pixels_per_unit is
+    // how many pixels one unit of radius covers
+    pub fn fit_to_screen(mut self, pixels_per_unit: f32) -> Self {
+        self.segments = segments_for(self.radius * pixels_per_unit);
+        self
+    }
 }
 
 pub struct Triangle {
@@ -47,35 +101,499 @@ impl Triangle {
 }
 
 impl Shape for Circle {
+    // a fan from the center out to a closed ring of rim points
     fn get_arrays(&self) -> Vec<f32> {
+        let rim = arc_points(self.pos, (self.radius, self.radius), 0., TAU, self.segments);
+
+        let mut v = Vec::new();
+        push_fan(&mut v, (self.pos[X], self.pos[Y]), &rim);
+        v
+    }
 
-        let (cx, cy) = (self.pos[X], self.pos[Y]);
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+    }
+}
 
-        let res = Circle::resolution();
+impl Shape for Square {
+    fn get_arrays(&self) -> Vec<f32> {
+        let (x, y, w, h) = (self.pos[X], self.pos[Y], self.size[X], self.size[Y]);
+        let corners = [(x, y), (x + w, y), (x + w, y + h), (x, y + h)];
+
+        // pass raw verts to the vbo, then we can use an algorithm to create unqiue list with ebo's
         let mut v = Vec::new();
-        let step = std::f32::consts::TAU / (res as f32);
+        for i in [0, 3, 1, 2, 1, 3] {
+            v.push(corners[i].0);
+            v.push(corners[i].1);
+            if let Some(colors) = &self.colors {
+                v.extend_from_slice(&colors[i].to_rgba_f32());
+            }
+        }
+        v
+    }
+
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+        if self.colors.is_some() {
+            vao.add_attribute(vbo, &VertexColorInfo);
+        }
+    }
+
+    fn vertex_floats(&self) -> usize {
+        if self.colors.is_some() { 6 } else { 2 }
+    }
+}
+
+impl Shape for Triangle {
+    #[rustfmt::skip]
+    fn get_arrays(&self) -> Vec<f32> {
+        vec![
+            self.pos1[X], self.pos1[Y], 
+            self.pos2[X], self.pos2[Y], 
+            self.pos3[X], self.pos3[Y], 
+        ]
+    }
+    
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+    }
+}
+
+// same thing, pos is the corner and size can be anything
+pub type Rectangle = Square;
+
+// smallest number of segments any curved shape will use
+const MIN_SEGMENTS: usize = 3;
+// and the most, past this huge circles just get a little flat
+const MAX_SEGMENTS: usize = 1024;
+// in pixels, how far a rim edge can cut inside the real curve
+const MAX_ROUND_ERROR: f32 = 0.25;
+// what new fits round shapes to, one unit is half the height of the default 800x600 camera
+const DEFAULT_PIXELS_PER_UNIT: f32 = 300.;
+
+// each edge covers the angle whose chord sags by the error, so small circles get few and
+// big ones get more
+fn segments_for(radius_in_pixels: f32) -> usize {
+    // nan lands here too
+    if radius_in_pixels.is_nan() || radius_in_pixels <= MAX_ROUND_ERROR {
+        return MIN_SEGMENTS;
+    }
+
+    let step = 2. * (1. - MAX_ROUND_ERROR / radius_in_pixels).acos();
+    ((TAU / step).ceil() as usize).clamp(MIN_SEGMENTS, MAX_SEGMENTS)
+}
+
+type Point = (f32, f32);
+
+fn point_on(center: Vec2, radii: Point, angle: f32) -> Point {
+    let (sin, cos) = angle.sin_cos();
+    (center[X] + cos * radii.0, center[Y] + sin * radii.1)
+}
+
+// points from start to end angle inclusive, segments is for a whole turn and a partial
+// sweep gets its share of them
+fn arc_points(
+    center: Vec2,
+    radii: Point,
+    start: f32,
+    end: f32,
+    segments: usize,
+) -> Vec<Point> {
+    let sweep = end - start;
+    let count = ((segments as f32 * sweep.abs() / TAU).ceil() as usize).max(1);
+
+    (0..=count)
+        .map(|i| point_on(center, radii, start + sweep * i as f32 / count as f32))
+        .collect()
+}
+
+fn push_triangle(v: &mut Vec<f32>, a: Point, b: Point, c: Point) {
+    v.extend_from_slice(&[a.0, a.1, b.0, b.1, c.0, c.1]);
+}
+
+// a triangle from the center to every pair of neighbouring rim points, the rim has to
+// already be closed if the fan should go all the way around
+fn push_fan(v: &mut Vec<f32>, center: Point, rim: &[Point]) {
+    for pair in rim.windows(2) {
+        push_triangle(v, center, pair[0], pair[1]);
+    }
+}
+
+// two triangles between each step of two rims with the same number of points
+fn push_band(v: &mut Vec<f32>, inner: &[Point], outer: &[Point]) {
+    for i in 0..inner.len().min(outer.len()).saturating_sub(1) {
+        push_triangle(v, inner[i], outer[i], outer[i + 1]);
+        push_triangle(v, inner[i], outer[i + 1], inner[i + 1]);
+    }
+}
 
-        let len = self.radius;
+fn center_of(points: &[Point]) -> Point {
+    let n = points.len().max(1) as f32;
+    let (x, y) = points.iter().fold((0., 0.), |(x, y), p| (x + p.0, y + p.1));
+    (x / n, y / n)
+}
+
+pub struct Ellipse {
+    pub pos: Vec2,
+    pub radii: Vec2,
+    pub segments: usize,
+}
+
+impl Ellipse {
+    pub fn new(pos: Vec2, radii: Vec2) -> Self {
+        Self {
+            pos,
+            radii,
+            segments: MIN_SEGMENTS,
+        }
+        .fit_to_screen(DEFAULT_PIXELS_PER_UNIT)
+    }
+
+    pub fn with_segments(mut self, segments: usize) -> Self {
+        self.segments = segments.max(MIN_SEGMENTS);
+        self
+    }
+
+    pub fn fit_to_screen(mut self, pixels_per_unit: f32) -> Self {
+        self.segments = segments_for(self.radii[X].abs().max(self.radii[Y].abs()) * pixels_per_unit);
+        self
+    }
+}
+
+pub struct RoundedRect {
+    pub pos: Vec2,
+    pub size: Vec2,
+    pub radius: f32,
+    // per corner
+    pub segments: usize,
+}
+
+impl RoundedRect {
+    pub fn new(pos: Vec2, size: Vec2, radius: f32) -> Self {
+        Self {
+            pos,
+            size,
+            radius,
+            segments: 1,
+        }
+        .fit_to_screen(DEFAULT_PIXELS_PER_UNIT)
+    }
+
+    pub fn with_segments(mut self, segments: usize) -> Self {
+        self.segments = segments.max(1);
+        self
+    }
+
+    // a quarter of what a whole circle with the corner radius would get
+    pub fn fit_to_screen(mut self, pixels_per_unit: f32) -> Self {
+        self.segments = segments_for(self.radius * pixels_per_unit).div_ceil(4);
+        self
+    }
+
+    fn rim(&self) -> Vec<Point> {
+        let (x, y, w, h) = (self.pos[X], self.pos[Y], self.size[X], self.size[Y]);
+        let r = self.radius.clamp(0., w.abs().min(h.abs()) / 2.);
+
+        // corner centers going around from the bottom right, each with a quarter turn
+        let corners = [
+            (x + w - r, y + r, -TAU / 4.),
+            (x + w - r, y + h - r, 0.),
+            (x + r, y + h - r, TAU / 4.),
+            (x + r, y + r, TAU / 2.),
+        ];
+
+        let mut points = Vec::new();
+        for (cx, cy, start) in corners {
+            points.extend(arc_points(
+                vec2(cx, cy),
+                (r, r),
+                start,
+                start + TAU / 4.,
+                self.segments * 4,
+            ));
+        }
+        points
+    }
+}
+
+pub struct RegularPolygon {
+    pub pos: Vec2,
+    pub radius: f32,
+    pub sides: usize,
+    // radians, at 0 the first corner points along +x
+    pub rotation: f32,
+}
+
+impl RegularPolygon {
+    pub fn new(pos: Vec2, radius: f32, sides: usize) -> Self {
+        Self {
+            pos,
+            radius,
+            sides: sides.max(MIN_SEGMENTS),
+            rotation: 0.,
+        }
+    }
+}
+
+pub struct Star {
+    pub pos: Vec2,
+    pub points: usize,
+    pub outer_radius: f32,
+    pub inner_radius: f32,
+    // radians, at 0 the first point points along +x
+    pub rotation: f32,
+}
+
+impl Star {
+    pub fn new(pos: Vec2, points: usize, outer_radius: f32, inner_radius: f32) -> Self {
+        Self {
+            pos,
+            points: points.max(2),
+            outer_radius,
+            inner_radius,
+            rotation: 0.,
+        }
+    }
+
+    // alternates outer and inner corners and ends back on the first,
+    // the star is always visible from its center
+    fn rim(&self) -> Vec<Point> {
+        let corners = self.points * 2;
+        (0..=corners)
+            .map(|i| {
+                let r = if i % 2 == 0 {
+                    self.outer_radius
+                } else {
+                    self.inner_radius
+                };
+                point_on(
+                    self.pos,
+                    (r, r),
+                    self.rotation + TAU * i as f32 / corners as f32,
+                )
+            })
+            .collect()
+    }
+}
+
+// a curved band thickness wide centered on radius, angles are in radians
+pub struct Arc {
+    pub pos: Vec2,
+    pub radius: f32,
+    pub thickness: f32,
+    pub start: f32,
+    pub end: f32,
+    pub segments: usize,
+}
+
+impl Arc {
+    pub fn new(pos: Vec2, radius: f32, thickness: f32, start: f32, end: f32) -> Self {
+        Self {
+            pos,
+            radius,
+            thickness,
+            start,
+            end,
+            segments: MIN_SEGMENTS,
+        }
+        .fit_to_screen(DEFAULT_PIXELS_PER_UNIT)
+    }
+
+    pub fn with_segments(mut self, segments: usize) -> Self {
+        self.segments = segments.max(MIN_SEGMENTS);
+        self
+    }
 
-        let mut last_point = (1f32, 0f32);
+    pub fn fit_to_screen(mut self, pixels_per_unit: f32) -> Self {
+        self.segments = segments_for((self.radius + self.thickness / 2.) * pixels_per_unit);
+        self
+    }
+}
 
-        for i in 1..res+1 {
-            let turn = i as f32 * step;
+// a filled slice of a circle, angles are in radians
+pub struct Pie {
+    pub pos: Vec2,
+    pub radius: f32,
+    pub start: f32,
+    pub end: f32,
+    pub segments: usize,
+}
 
-            let (y, x) = turn.sin_cos();
+impl Pie {
+    pub fn new(pos: Vec2, radius: f32, start: f32, end: f32) -> Self {
+        Self {
+            pos,
+            radius,
+            start,
+            end,
+            segments: MIN_SEGMENTS,
+        }
+        .fit_to_screen(DEFAULT_PIXELS_PER_UNIT)
+    }
 
-            v.push(cx);
-            v.push(cy);
+    pub fn with_segments(mut self, segments: usize) -> Self {
+        self.segments = segments.max(MIN_SEGMENTS);
+        self
+    }
 
-            v.push(last_point.0 * len);
-            v.push(last_point.1 * len);
+    pub fn fit_to_screen(mut self, pixels_per_unit: f32) -> Self {
+        self.segments = segments_for(self.radius * pixels_per_unit);
+        self
+    }
+}
 
-            v.push(x * len);
-            v.push(y * len);
+pub struct Ring {
+    pub pos: Vec2,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub segments: usize,
+}
 
-            last_point = (x, y);
+impl Ring {
+    pub fn new(pos: Vec2, inner_radius: f32, outer_radius: f32) -> Self {
+        Self {
+            pos,
+            inner_radius,
+            outer_radius,
+            segments: MIN_SEGMENTS,
         }
+        .fit_to_screen(DEFAULT_PIXELS_PER_UNIT)
+    }
 
+    pub fn with_segments(mut self, segments: usize) -> Self {
+        self.segments = segments.max(MIN_SEGMENTS);
+        self
+    }
+
+    pub fn fit_to_screen(mut self, pixels_per_unit: f32) -> Self {
+        self.segments = segments_for(self.outer_radius * pixels_per_unit);
+        self
+    }
+}
+
+pub struct Line {
+    pub from: Vec2,
+    pub to: Vec2,
+    pub thickness: f32,
+}
+
+impl Line {
+    pub fn new(from: Vec2, to: Vec2, thickness: f32) -> Self {
+        Self {
+            from,
+            to,
+            thickness,
+        }
+    }
+}
+
+// connected lines, the corners are mitered so thick lines don't get gaps
+pub struct Polyline {
+    pub points: Vec<Vec2>,
+    pub thickness: f32,
+    // joins the last point back to the first
+    pub closed: bool,
+}
+
+impl Polyline {
+    pub fn new(points: Vec<Vec2>, thickness: f32) -> Self {
+        Self {
+            points,
+            thickness,
+            closed: false,
+        }
+    }
+
+    pub fn closed(mut self) -> Self {
+        self.closed = true;
+        self
+    }
+
+    // the two edges of the line at each point, left and right of the direction of travel
+    fn edges(&self) -> (Vec<Point>, Vec<Point>) {
+        let points: Vec<Point> = self.points.iter().map(|p| (p[X], p[Y])).collect();
+        let n = points.len();
+        let half = self.thickness / 2.;
+
+        let normal = |a: Point, b: Point| {
+            let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+            let len = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+            (-dy / len, dx / len)
+        };
+
+        let mut left = Vec::with_capacity(n);
+        let mut right = Vec::with_capacity(n);
+
+        for i in 0..n {
+            let prev = if i > 0 {
+                Some(points[i - 1])
+            } else if self.closed {
+                Some(points[n - 1])
+            } else {
+                None
+            };
+            let next = if i + 1 < n {
+                Some(points[i + 1])
+            } else if self.closed {
+                Some(points[0])
+            } else {
+                None
+            };
+
+            let p = points[i];
+            let (nx, ny, scale) = match (prev, next) {
+                (Some(a), Some(b)) => {
+                    let n0 = normal(a, p);
+                    let n1 = normal(p, b);
+                    let (mx, my) = (n0.0 + n1.0, n0.1 + n1.1);
+                    let len = (mx * mx + my * my).sqrt();
+                    if len < 1e-4 {
+                        // doubles straight back on itself
+                        (n0.0, n0.1, 1.)
+                    } else {
+                        let (mx, my) = (mx / len, my / len);
+                        // longer the sharper the corner, capped so spikes stay reasonable
+                        let cos = mx * n1.0 + my * n1.1;
+                        (mx, my, (1. / cos.max(0.25)))
+                    }
+                }
+                (Some(a), None) => {
+                    let n = normal(a, p);
+                    (n.0, n.1, 1.)
+                }
+                (None, Some(b)) => {
+                    let n = normal(p, b);
+                    (n.0, n.1, 1.)
+                }
+                (None, None) => (0., 0., 0.),
+            };
+
+            let offset = (nx * half * scale, ny * half * scale);
+            left.push((p.0 + offset.0, p.1 + offset.1));
+            right.push((p.0 - offset.0, p.1 - offset.1));
+        }
+
+        if self.closed && n > 0 {
+            left.push(left[0]);
+            right.push(right[0]);
+        }
+
+        (left, right)
+    }
+}
+
+impl Shape for Ellipse {
+    fn get_arrays(&self) -> Vec<f32> {
+        let rim = arc_points(
+            self.pos,
+            (self.radii[X], self.radii[Y]),
+            0.,
+            TAU,
+            self.segments,
+        );
+
+        let mut v = Vec::new();
+        push_fan(&mut v, (self.pos[X], self.pos[Y]), &rim);
         v
     }
 
@@ -84,20 +602,15 @@ impl Shape for Circle {
     }
 }
 
-impl Shape for Square {
-    #[rustfmt::skip]
+impl Shape for RoundedRect {
     fn get_arrays(&self) -> Vec<f32> {
-        // pass raw verts to the vbo, then we can use an algorithm to create unqiue list with ebo's
-        vec![
-            // firs tri
-            self.pos[X], self.pos[Y], 
-            self.pos[X], self.pos[Y] + self.size[Y],
-            self.pos[X] + self.size[X], self.pos[Y],
-            // second try
-            self.pos[X] + self.size[X], self.pos[Y] + self.size[Y],
-            self.pos[X] + self.size[X], self.pos[Y],
-            self.pos[X], self.pos[Y] + self.size[Y],
-        ]
+        let mut rim = self.rim();
+        let center = center_of(&rim);
+        rim.push(rim[0]);
+
+        let mut v = Vec::new();
+        push_fan(&mut v, center, &rim);
+        v
     }
 
     fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
@@ -105,17 +618,237 @@ impl Shape for Square {
     }
 }
 
-impl Shape for Triangle {
-    #[rustfmt::skip]
+impl Shape for RegularPolygon {
     fn get_arrays(&self) -> Vec<f32> {
-        vec![
-            self.pos1[X], self.pos1[Y], 
-            self.pos2[X], self.pos2[Y], 
-            self.pos3[X], self.pos3[Y], 
-        ]
+        let rim = arc_points(
+            self.pos,
+            (self.radius, self.radius),
+            self.rotation,
+            self.rotation + TAU,
+            self.sides,
+        );
+
+        let mut v = Vec::new();
+        push_fan(&mut v, (self.pos[X], self.pos[Y]), &rim);
+        v
     }
-    
+
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+    }
+}
+
+impl Shape for Star {
+    fn get_arrays(&self) -> Vec<f32> {
+        let mut v = Vec::new();
+        push_fan(&mut v, (self.pos[X], self.pos[Y]), &self.rim());
+        v
+    }
+
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+    }
+}
+
+impl Shape for Arc {
+    fn get_arrays(&self) -> Vec<f32> {
+        let inner = (self.radius - self.thickness / 2.).max(0.);
+        let outer = self.radius + self.thickness / 2.;
+
+        let inner = arc_points(
+            self.pos,
+            (inner, inner),
+            self.start,
+            self.end,
+            self.segments,
+        );
+        let outer = arc_points(
+            self.pos,
+            (outer, outer),
+            self.start,
+            self.end,
+            self.segments,
+        );
+
+        let mut v = Vec::new();
+        push_band(&mut v, &inner, &outer);
+        v
+    }
+
     fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
         vao.add_attribute(vbo, &VertexPosInfo2D);
     }
 }
+
+impl Shape for Pie {
+    fn get_arrays(&self) -> Vec<f32> {
+        let rim = arc_points(
+            self.pos,
+            (self.radius, self.radius),
+            self.start,
+            self.end,
+            self.segments,
+        );
+
+        let mut v = Vec::new();
+        push_fan(&mut v, (self.pos[X], self.pos[Y]), &rim);
+        v
+    }
+
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+    }
+}
+
+impl Shape for Ring {
+    fn get_arrays(&self) -> Vec<f32> {
+        let (r0, r1) = (self.inner_radius, self.outer_radius);
+        let inner = arc_points(self.pos, (r0, r0), 0., TAU, self.segments);
+        let outer = arc_points(self.pos, (r1, r1), 0., TAU, self.segments);
+
+        let mut v = Vec::new();
+        push_band(&mut v, &inner, &outer);
+        v
+    }
+
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+    }
+}
+
+impl Shape for Line {
+    fn get_arrays(&self) -> Vec<f32> {
+        Polyline::new(vec![self.from, self.to], self.thickness).get_arrays()
+    }
+
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+    }
+}
+
+impl Shape for Polyline {
+    fn get_arrays(&self) -> Vec<f32> {
+        let (left, right) = self.edges();
+
+        let mut v = Vec::new();
+        push_band(&mut v, &right, &left);
+        v
+    }
+
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+    }
+}
+
+#[cfg(test)]
+fn area(v: &[f32]) -> f32 {
+    v.chunks(6)
+        .map(|t| ((t[2] - t[0]) * (t[5] - t[1]) - (t[4] - t[0]) * (t[3] - t[1])).abs() / 2.)
+        .sum()
+}
+
+#[test]
+fn test_curved_shape_areas() {
+    use std::f32::consts::PI;
+
+    let ellipse = Ellipse::new(vec2(3., -2.), vec2(2., 1.)).with_segments(256);
+    assert!((area(&ellipse.get_arrays()) - PI * 2.).abs() < 0.01);
+
+    let ring = Ring::new(vec2(1., 1.), 1., 2.).with_segments(256);
+    assert!((area(&ring.get_arrays()) - PI * 3.).abs() < 0.01);
+
+    let pie = Pie::new(vec2(0., 0.), 1., 0., PI / 2.).with_segments(256);
+    assert!((area(&pie.get_arrays()) - PI / 4.).abs() < 0.01);
+
+    let arc = Arc::new(vec2(0., 0.), 1.5, 1., 0., PI).with_segments(256);
+    assert!((area(&arc.get_arrays()) - PI * 3. / 2.).abs() < 0.01);
+
+    // corners cut away leave 4 - (4 - pi) * r^2
+    let rounded = RoundedRect::new(vec2(-1., -1.), vec2(2., 2.), 0.5).with_segments(64);
+    assert!((area(&rounded.get_arrays()) - (4. - (4. - PI) * 0.25)).abs() < 0.01);
+}
+
+#[test]
+fn test_segment_counts() {
+    let ellipse = Ellipse::new(vec2(0., 0.), vec2(1., 1.)).with_segments(10);
+    assert_eq!(ellipse.get_arrays().len(), 10 * 6);
+
+    // half a turn gets half the segments
+    let pie = Pie::new(vec2(0., 0.), 1., 0., TAU / 2.).with_segments(10);
+    assert_eq!(pie.get_arrays().len(), 5 * 6);
+
+    let hexagon = RegularPolygon::new(vec2(0., 0.), 1., 6);
+    assert_eq!(hexagon.get_arrays().len(), 6 * 6);
+    assert!((area(&hexagon.get_arrays()) - 3. * 3f32.sqrt() / 2.).abs() < 1e-4);
+
+    let star = Star::new(vec2(0., 0.), 5, 2., 1.);
+    assert_eq!(star.get_arrays().len(), 10 * 6);
+}
+
+#[test]
+fn test_lines() {
+    let line = Line::new(vec2(0., 0.), vec2(4., 0.), 1.);
+    assert!((area(&line.get_arrays()) - 4.).abs() < 1e-5);
+
+    // a right angle miters into two full rectangles meeting at the corner
+    let corner = Polyline::new(vec![vec2(0., 0.), vec2(4., 0.), vec2(4., 4.)], 1.);
+    assert!((area(&corner.get_arrays()) - 8.).abs() < 1e-4);
+
+    let square = Polyline::new(
+        vec![vec2(0., 0.), vec2(4., 0.), vec2(4., 4.), vec2(0., 4.)],
+        1.,
+    )
+    .closed();
+    assert!((area(&square.get_arrays()) - (25. - 9.)).abs() < 1e-4);
+}
+
+#[test]
+fn test_circle_rim() {
+    for (x, y, radius) in [(0., 0., 1.), (3., -2., 0.5), (-100., 40., 25.)] {
+        let circle = Circle::new(vec2(x, y), radius).with_segments(24);
+        let v = circle.get_arrays();
+        assert_eq!(v.len(), 24 * 6);
+
+        for tri in v.chunks(6) {
+            // the first corner is the center and the other two are on the rim
+            assert_eq!((tri[0], tri[1]), (x, y));
+            for rim in tri[2..].chunks(2) {
+                let distance = ((rim[0] - x).powi(2) + (rim[1] - y).powi(2)).sqrt();
+                assert!((distance - radius).abs() < radius * 1e-5);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_adaptive_segments() {
+    // tiny circles still get a triangle and huge ones stop somewhere
+    assert_eq!(Circle::segments_for(0.), MIN_SEGMENTS);
+    assert_eq!(Circle::segments_for(f32::NAN), MIN_SEGMENTS);
+    assert_eq!(Circle::segments_for(1e9), MAX_SEGMENTS);
+
+    let mut last = 0;
+    for radius in [1., 4., 16., 64., 256.] {
+        let segments = Circle::segments_for(radius);
+        assert!(segments >= last);
+        last = segments;
+
+        // the middle of each edge sags no more than the error allowed
+        let sag = radius * (1. - (TAU / segments as f32 / 2.).cos());
+        assert!(sag <= MAX_ROUND_ERROR + 1e-4);
+    }
+
+    // the same circle at twice the size on screen needs more
+    let small = Circle::new(vec2(0., 0.), 0.5).fit_to_screen(100.);
+    let big = Circle::new(vec2(0., 0.), 0.5).fit_to_screen(200.);
+    assert!(big.segments > small.segments);
+
+    // without fit_to_screen the size still counts
+    assert!(Circle::new(vec2(0., 0.), 0.8).segments > Circle::new(vec2(0., 0.), 0.05).segments);
+    assert_eq!(Ring::new(vec2(0., 0.), 0.4, 0.8).segments, Circle::new(vec2(0., 0.), 0.8).segments);
+
+    // a rounded corner is a quarter of the circle with its radius
+    let rounded = RoundedRect::new(vec2(0., 0.), vec2(2., 2.), 0.5);
+    assert_eq!(rounded.segments, Circle::new(vec2(0., 0.), 0.5).segments.div_ceil(4));
+    assert!(rounded.segments > RoundedRect::new(vec2(0., 0.), vec2(2., 2.), 0.02).segments);
+}
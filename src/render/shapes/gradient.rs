@@ -0,0 +1,362 @@
+use crate::math::color::Color;
+use crate::math::vector::*;
+use crate::render::ogl::{Vao, Vbo, VertexColorInfo, VertexPosInfo2D};
+
+use super::{Point, Shape};
+
+// radial gradients bend the color across a triangle, so they get cut up until no edge is
+// longer than this much of the radius
+const RADIAL_STEP: f32 = 1. / 8.;
+const MAX_SPLITS: u32 = 5;
+
+type Rgba = [f32; 4];
+
+#[derive(Debug, Clone, Copy)]
+pub enum GradientKind {
+    // along the line, anything past either end keeps that end's color
+    Linear { from: Vec2, to: Vec2 },
+    // out from the center, the last stop carries on past radius
+    Radial { center: Vec2, radius: f32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    // sorted by where they are from 0 to 1
+    stops: Vec<(f32, Rgba)>,
+}
+
+impl Gradient {
+    pub fn linear(from: Vec2, to: Vec2, start: Color, end: Color) -> Self {
+        Self {
+            kind: GradientKind::Linear { from, to },
+            stops: vec![(0., start.to_rgba_f32()), (1., end.to_rgba_f32())],
+        }
+    }
+
+    pub fn radial(center: Vec2, radius: f32, inner: Color, outer: Color) -> Self {
+        Self {
+            kind: GradientKind::Radial { center, radius },
+            stops: vec![(0., inner.to_rgba_f32()), (1., outer.to_rgba_f32())],
+        }
+    }
+
+    // another color somewhere between the ends, at is clamped to 0 to 1
+    pub fn with_stop(mut self, at: f32, color: Color) -> Self {
+        let at = at.clamp(0., 1.);
+        let index = self.stops.partition_point(|(t, _)| *t <= at);
+        self.stops.insert(index, (at, color.to_rgba_f32()));
+        self
+    }
+
+    // how far along the gradient the point is, not clamped
+    fn position(&self, p: Point) -> f32 {
+        match self.kind {
+            GradientKind::Linear { from, to } => {
+                let (dx, dy) = (to[X] - from[X], to[Y] - from[Y]);
+                let len2 = dx * dx + dy * dy;
+                if len2 == 0. {
+                    return 0.;
+                }
+                ((p.0 - from[X]) * dx + (p.1 - from[Y]) * dy) / len2
+            }
+            GradientKind::Radial { center, radius } => {
+                let distance = ((p.0 - center[X]).powi(2) + (p.1 - center[Y]).powi(2)).sqrt();
+                if radius > 0. {
+                    distance / radius
+                } else {
+                    1.
+                }
+            }
+        }
+    }
+
+    fn color_at_position(&self, t: f32) -> Rgba {
+        let index = self.stops.partition_point(|(at, _)| *at <= t);
+        if index == 0 {
+            return self.stops[0].1;
+        }
+        if index == self.stops.len() {
+            return self.stops[index - 1].1;
+        }
+
+        let ((t0, c0), (t1, c1)) = (self.stops[index - 1], self.stops[index]);
+        let k = if t1 > t0 { (t - t0) / (t1 - t0) } else { 1. };
+        [0, 1, 2, 3].map(|i| c0[i] + (c1[i] - c0[i]) * k)
+    }
+
+    // rgba from 0 to 1
+    pub fn color_at(&self, p: Vec2) -> [f32; 4] {
+        self.color_at_position(self.position((p[X], p[Y])))
+    }
+
+    pub fn fill<S: Shape>(self, shape: S) -> GradientFill<S> {
+        GradientFill {
+            shape,
+            gradient: self,
+        }
+    }
+}
+
+// any shape colored in by a gradient, draw it with Material::vertex_colors
+pub struct GradientFill<S> {
+    pub shape: S,
+    pub gradient: Gradient,
+}
+
+impl<S: Shape> Shape for GradientFill<S> {
+    fn get_arrays(&self) -> Vec<f32> {
+        let stride = self.shape.vertex_floats();
+        let points: Vec<Point> = self
+            .shape
+            .get_arrays()
+            .chunks_exact(stride)
+            .map(|v| (v[0], v[1]))
+            .collect();
+
+        let mut triangles = Vec::new();
+        for tri in points.chunks_exact(3) {
+            match self.gradient.kind {
+                GradientKind::Linear { .. } => {
+                    self.split_at_stops([tri[0], tri[1], tri[2]], &mut triangles)
+                }
+                GradientKind::Radial { radius, .. } => subdivide(
+                    [tri[0], tri[1], tri[2]],
+                    radius * RADIAL_STEP,
+                    0,
+                    &mut triangles,
+                ),
+            }
+        }
+
+        let mut v = Vec::with_capacity(triangles.len() * 18);
+        for tri in triangles {
+            for p in tri {
+                v.extend_from_slice(&[p.0, p.1]);
+                v.extend_from_slice(&self.gradient.color_at_position(self.gradient.position(p)));
+            }
+        }
+        v
+    }
+
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+        vao.add_attribute(vbo, &VertexColorInfo);
+    }
+
+    fn vertex_floats(&self) -> usize {
+        6
+    }
+}
+
+impl<S> GradientFill<S> {
+    // a linear gradient is a straight ramp between two stops, so cutting the triangle along
+    // every stop makes the colors between the vertices exactly right
+    fn split_at_stops(&self, tri: [Point; 3], out: &mut Vec<[Point; 3]>) {
+        let mut pieces = vec![tri.to_vec()];
+        for (at, _) in &self.gradient.stops {
+            let mut next = Vec::new();
+            for piece in pieces {
+                let (below, above) = clip(&piece, |p| self.gradient.position(p) - at);
+                next.extend([below, above].into_iter().filter(|p| p.len() >= 3));
+            }
+            pieces = next;
+        }
+
+        // every piece is convex, clipping a triangle by lines keeps it that way
+        for piece in pieces {
+            for i in 1..piece.len() - 1 {
+                out.push([piece[0], piece[i], piece[i + 1]]);
+            }
+        }
+    }
+}
+
+// the parts of a convex polygon on either side of where f is zero
+fn clip(polygon: &[Point], f: impl Fn(Point) -> f32) -> (Vec<Point>, Vec<Point>) {
+    let (mut below, mut above) = (Vec::new(), Vec::new());
+
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        let (fa, fb) = (f(a), f(b));
+
+        if fa <= 0. {
+            below.push(a);
+        }
+        if fa >= 0. {
+            above.push(a);
+        }
+        if (fa < 0. && fb > 0.) || (fa > 0. && fb < 0.) {
+            let t = fa / (fa - fb);
+            let cut = (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+            below.push(cut);
+            above.push(cut);
+        }
+    }
+
+    (below, above)
+}
+
+// four triangles from the edge midpoints until every edge is short enough
+fn subdivide(tri: [Point; 3], max_edge: f32, depth: u32, out: &mut Vec<[Point; 3]>) {
+    let length = |a: Point, b: Point| ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+    let longest = length(tri[0], tri[1])
+        .max(length(tri[1], tri[2]))
+        .max(length(tri[2], tri[0]));
+
+    if depth >= MAX_SPLITS || longest <= max_edge {
+        out.push(tri);
+        return;
+    }
+
+    let mid = |a: Point, b: Point| ((a.0 + b.0) / 2., (a.1 + b.1) / 2.);
+    let (ab, bc, ca) = (
+        mid(tri[0], tri[1]),
+        mid(tri[1], tri[2]),
+        mid(tri[2], tri[0]),
+    );
+    for piece in [
+        [tri[0], ab, ca],
+        [ab, tri[1], bc],
+        [ca, bc, tri[2]],
+        [ab, bc, ca],
+    ] {
+        subdivide(piece, max_edge, depth + 1, out);
+    }
+}
+
+#[cfg(test)]
+fn vertices(shape: &impl Shape) -> Vec<[f32; 6]> {
+    assert_eq!(shape.vertex_floats(), 6);
+    shape
+        .get_arrays()
+        .chunks_exact(6)
+        .map(|v| [v[0], v[1], v[2], v[3], v[4], v[5]])
+        .collect()
+}
+
+#[test]
+fn test_linear() {
+    use super::Square;
+
+    // red on the left to blue on the right with green in the middle
+    let gradient = Gradient::linear(vec2(0., 0.), vec2(2., 0.), Color::RED, Color::BLUE)
+        .with_stop(0.5, Color::GREEN);
+    let fill = gradient.fill(Square::new(vec2(0., 0.), vec2(2., 1.)));
+
+    let vertices = vertices(&fill);
+    assert_eq!(vertices.len() % 3, 0);
+
+    // cut along x = 1, and no triangle crosses it
+    assert!(vertices.iter().any(|v| (v[0] - 1.).abs() < 1e-6));
+    for tri in vertices.chunks(3) {
+        let left = tri.iter().all(|v| v[0] <= 1. + 1e-6);
+        let right = tri.iter().all(|v| v[0] >= 1. - 1e-6);
+        assert!(left || right);
+    }
+
+    for v in &vertices {
+        let expected = fill.gradient.color_at(vec2(v[0], v[1]));
+        assert_eq!(v[2..], expected[..]);
+        if (v[0] - 1.).abs() < 1e-6 {
+            assert!((v[3] - 1.).abs() < 1e-5);
+        }
+    }
+
+    // nothing lost in the cutting
+    let area: f32 = vertices
+        .chunks(3)
+        .map(|t| {
+            ((t[1][0] - t[0][0]) * (t[2][1] - t[0][1]) - (t[2][0] - t[0][0]) * (t[1][1] - t[0][1]))
+                .abs()
+                / 2.
+        })
+        .sum();
+    assert!((area - 2.).abs() < 1e-5);
+}
+
+#[test]
+fn test_radial() {
+    use super::Circle;
+
+    let gradient = Gradient::radial(vec2(1., 1.), 2., Color::WHITE, Color::BLACK);
+    let fill = gradient.fill(Circle::new(vec2(1., 1.), 2.));
+
+    let vertices = vertices(&fill);
+    // the fan got cut up so the color can bend
+    assert!(vertices.len() > Circle::resolution() * 3);
+    for v in &vertices {
+        let distance = ((v[0] - 1.).powi(2) + (v[1] - 1.).powi(2)).sqrt();
+        let shade = 1. - distance / 2.;
+        assert!(v[2..5].iter().all(|c| (c - shade).abs() < 1e-4));
+        assert_eq!(v[5], 1.);
+    }
+
+    // past the radius it stays the outer color
+    assert_eq!(fill.gradient.color_at(vec2(10., 10.)), [0., 0., 0., 1.]);
+}
+
+#[test]
+fn test_square_corner_colors() {
+    use super::Square;
+
+    let square = Square::new(vec2(0., 0.), vec2(1., 1.)).with_corner_colors([
+        Color::RED,
+        Color::GREEN,
+        Color::BLUE,
+        Color::rgba(255, 255, 255, 0),
+    ]);
+    let vertices = vertices(&square);
+    assert_eq!(vertices.len(), 6);
+
+    for v in vertices {
+        let expected = match (v[0], v[1]) {
+            (0., 0.) => [1., 0., 0., 1.],
+            (1., 0.) => [0., 1., 0., 1.],
+            (1., 1.) => [0., 0., 1., 1.],
+            _ => [1., 1., 1., 0.],
+        };
+        assert_eq!(v[2..], expected[..]);
+    }
+
+    // without colors it stays positions only
+    assert_eq!(Square::new(vec2(0., 0.), vec2(1., 1.)).vertex_floats(), 2);
+}
+
+#[test]
+#[cfg_attr(not(feature = "egl"), ignore = "needs a gl context (--features egl)")]
+fn test_stop_pixels() {
+    use super::Square;
+    use crate::math::transform::Transform2D;
+    use crate::render::golden::render;
+    use crate::render::ogl::{DrawStream, Material};
+
+    // across the whole screen, with the blue end half see through
+    let gradient = Gradient::linear(
+        vec2(-1., 0.),
+        vec2(1., 0.),
+        Color::RED,
+        Color::rgba(0, 0, 255, 128),
+    )
+    .with_stop(0.5, Color::GREEN);
+    let fill = gradient
+        .clone()
+        .fill(Square::new(vec2(-1., -1.), vec2(2., 2.)));
+    let image = render(128, 4, Color::BLACK, || {
+        DrawStream::from(fill).draw(&Material::vertex_colors(), &Transform2D::default());
+    });
+
+    // the pixels either side of the stop are nearly all green, one triangle from red to blue
+    // across the stop would make them both purple
+    for x in [0, 63, 64, 127] {
+        let center = (x as f32 + 0.5) / 64. - 1.;
+        let expected = gradient.color_at(vec2(center, 0.)).map(|c| c * 255.);
+        let pixel = image.get_pixel(x, 2).0;
+        for (got, want) in pixel.iter().zip(expected) {
+            assert!(
+                (*got as f32 - want).abs() <= 2.,
+                "{pixel:?} at {x}, wanted {expected:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,529 @@
+use std::f32::consts::TAU;
+
+use crate::math::vector::*;
+use crate::render::ogl::{Vao, Vbo, VertexPosInfo2D};
+
+use super::stroke::{Contour, Outline, Stroke, StrokeStyle};
+use super::{push_triangle, Point, Shape};
+
+// deepest a curve gets split in half when flattening, 2^16 pieces is plenty
+const MAX_DEPTH: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+    // inside wherever the outlines wind around a point at all
+    NonZero,
+    // inside wherever a point is wrapped an odd number of times, overlaps cut holes
+    EvenOdd,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Segment {
+    MoveTo(Point),
+    LineTo(Point),
+    QuadTo(Point, Point),
+    CubicTo(Point, Point, Point),
+    ArcTo {
+        radii: Point,
+        rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        to: Point,
+    },
+    Close,
+}
+
+// lines and curves like an svg path, fills as a triangle list or strokes through Stroke
+pub struct Path {
+    segments: Vec<Segment>,
+    // how far the flattened lines can stray from the real curve
+    pub tolerance: f32,
+    pub fill_rule: FillRule,
+}
+
+impl Default for Path {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn point(p: Vec2) -> Point {
+    (p[X], p[Y])
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self {
+            segments: Vec::new(),
+            tolerance: 0.01,
+            fill_rule: FillRule::NonZero,
+        }
+    }
+
+    // starts a new sub path, anything before it is left open unless it was closed
+    pub fn move_to(&mut self, to: Vec2) {
+        self.segments.push(Segment::MoveTo(point(to)));
+    }
+
+    pub fn line_to(&mut self, to: Vec2) {
+        self.segments.push(Segment::LineTo(point(to)));
+    }
+
+    pub fn quad_to(&mut self, control: Vec2, to: Vec2) {
+        self.segments
+            .push(Segment::QuadTo(point(control), point(to)));
+    }
+
+    pub fn cubic_to(&mut self, control1: Vec2, control2: Vec2, to: Vec2) {
+        self.segments.push(Segment::CubicTo(
+            point(control1),
+            point(control2),
+            point(to),
+        ));
+    }
+
+    // an elliptical arc to the point like svg's A command, rotation is in radians.
+    // of the four arcs that fit large_arc picks a long way round and sweep picks the
+    // one turning with positive angles
+    pub fn arc_to(&mut self, radii: Vec2, rotation: f32, large_arc: bool, sweep: bool, to: Vec2) {
+        self.segments.push(Segment::ArcTo {
+            radii: point(radii),
+            rotation,
+            large_arc,
+            sweep,
+            to: point(to),
+        });
+    }
+
+    // joins back to where the sub path started
+    pub fn close(&mut self) {
+        self.segments.push(Segment::Close);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    // every sub path as straight lines
+    pub fn flatten(&self) -> Vec<Contour> {
+        let mut contours = Vec::new();
+        let mut current: Vec<Point> = Vec::new();
+        let mut pen = (0., 0.);
+        let tolerance = self.tolerance.max(1e-5);
+
+        let finish = |current: &mut Vec<Point>, closed: bool, contours: &mut Vec<Contour>| {
+            if current.len() > 1 {
+                contours.push(Contour {
+                    points: current.iter().map(|p| vec2(p.0, p.1)).collect(),
+                    closed,
+                });
+            }
+            current.clear();
+        };
+
+        for segment in &self.segments {
+            if current.is_empty() && !matches!(segment, Segment::MoveTo(_) | Segment::Close) {
+                current.push(pen);
+            }
+
+            match *segment {
+                Segment::MoveTo(to) => {
+                    finish(&mut current, false, &mut contours);
+                    current.push(to);
+                    pen = to;
+                }
+                Segment::LineTo(to) => {
+                    current.push(to);
+                    pen = to;
+                }
+                Segment::QuadTo(c, to) => {
+                    flatten_quad(&mut current, pen, c, to, tolerance, 0);
+                    pen = to;
+                }
+                Segment::CubicTo(c1, c2, to) => {
+                    flatten_cubic(&mut current, pen, c1, c2, to, tolerance, 0);
+                    pen = to;
+                }
+                Segment::ArcTo {
+                    radii,
+                    rotation,
+                    large_arc,
+                    sweep,
+                    to,
+                } => {
+                    flatten_arc(
+                        &mut current,
+                        pen,
+                        radii,
+                        rotation,
+                        large_arc,
+                        sweep,
+                        to,
+                        tolerance,
+                    );
+                    pen = to;
+                }
+                Segment::Close => {
+                    if let Some(start) = current.first() {
+                        pen = *start;
+                    }
+                    finish(&mut current, true, &mut contours);
+                }
+            }
+        }
+        finish(&mut current, false, &mut contours);
+
+        contours
+    }
+
+    pub fn stroke(&self, style: StrokeStyle) -> Stroke {
+        Stroke::outline_of(self, style)
+    }
+}
+
+impl Outline for Path {
+    fn outline(&self) -> Vec<Contour> {
+        self.flatten()
+    }
+}
+
+impl Shape for Path {
+    // open sub paths are filled as if they were closed
+    fn get_arrays(&self) -> Vec<f32> {
+        let contours: Vec<Vec<Point>> = self
+            .flatten()
+            .iter()
+            .map(|c| c.points.iter().map(|p| point(*p)).collect())
+            .collect();
+
+        let mut v = Vec::new();
+        fill(&mut v, &contours, self.fill_rule);
+        v
+    }
+
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+    }
+}
+
+fn lerp(a: Point, b: Point, t: f32) -> Point {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+// how far p is from the line through a and b
+fn distance_to_line(p: Point, a: Point, b: Point) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len = (dx * dx + dy * dy).sqrt();
+    if len < 1e-9 {
+        return ((p.0 - a.0).powi(2) + (p.1 - a.1).powi(2)).sqrt();
+    }
+    ((p.0 - a.0) * dy - (p.1 - a.1) * dx).abs() / len
+}
+
+// splits in half until the control point is close enough to the chord, the curve never
+// strays more than half as far as its control point does
+fn flatten_quad(out: &mut Vec<Point>, a: Point, c: Point, b: Point, tolerance: f32, depth: u32) {
+    if depth >= MAX_DEPTH || distance_to_line(c, a, b) / 2. <= tolerance {
+        out.push(b);
+        return;
+    }
+
+    let (ac, cb) = (lerp(a, c, 0.5), lerp(c, b, 0.5));
+    let mid = lerp(ac, cb, 0.5);
+    flatten_quad(out, a, ac, mid, tolerance, depth + 1);
+    flatten_quad(out, mid, cb, b, tolerance, depth + 1);
+}
+
+// same idea, the hull of the control points bounds the curve
+fn flatten_cubic(
+    out: &mut Vec<Point>,
+    a: Point,
+    c1: Point,
+    c2: Point,
+    b: Point,
+    tolerance: f32,
+    depth: u32,
+) {
+    let flat = distance_to_line(c1, a, b).max(distance_to_line(c2, a, b)) * 0.75;
+    if depth >= MAX_DEPTH || flat <= tolerance {
+        out.push(b);
+        return;
+    }
+
+    let (p01, p12, p23) = (lerp(a, c1, 0.5), lerp(c1, c2, 0.5), lerp(c2, b, 0.5));
+    let (p012, p123) = (lerp(p01, p12, 0.5), lerp(p12, p23, 0.5));
+    let mid = lerp(p012, p123, 0.5);
+    flatten_cubic(out, a, p01, p012, mid, tolerance, depth + 1);
+    flatten_cubic(out, mid, p123, p23, b, tolerance, depth + 1);
+}
+
+// endpoint to center conversion from the svg spec, appendix F.6.5
+#[allow(clippy::too_many_arguments)]
+fn flatten_arc(
+    out: &mut Vec<Point>,
+    from: Point,
+    radii: Point,
+    rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    to: Point,
+    tolerance: f32,
+) {
+    let (mut rx, mut ry) = (radii.0.abs(), radii.1.abs());
+    if rx < 1e-6 || ry < 1e-6 || (from.0 == to.0 && from.1 == to.1) {
+        out.push(to);
+        return;
+    }
+
+    let (sin, cos) = rotation.sin_cos();
+    let (hx, hy) = ((from.0 - to.0) / 2., (from.1 - to.1) / 2.);
+    let (x1, y1) = (cos * hx + sin * hy, -sin * hx + cos * hy);
+
+    // radii too small to reach get scaled up until they just do
+    let scale = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if scale > 1. {
+        rx *= scale.sqrt();
+        ry *= scale.sqrt();
+    }
+
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut k = (num / den).max(0.).sqrt();
+    if large_arc == sweep {
+        k = -k;
+    }
+    let (cx1, cy1) = (k * rx * y1 / ry, -k * ry * x1 / rx);
+    let center = (
+        cos * cx1 - sin * cy1 + (from.0 + to.0) / 2.,
+        sin * cx1 + cos * cy1 + (from.1 + to.1) / 2.,
+    );
+
+    let angle = |ux: f32, uy: f32, vx: f32, vy: f32| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let start = angle(1., 0., (x1 - cx1) / rx, (y1 - cy1) / ry);
+    let mut delta = angle(
+        (x1 - cx1) / rx,
+        (y1 - cy1) / ry,
+        (-x1 - cx1) / rx,
+        (-y1 - cy1) / ry,
+    );
+    if !sweep && delta > 0. {
+        delta -= TAU;
+    } else if sweep && delta < 0. {
+        delta += TAU;
+    }
+
+    // the angle per step that keeps the chord within tolerance of the bigger radius
+    let r = rx.max(ry);
+    let step = 2. * (1. - (tolerance / r).min(1.)).acos();
+    let count = ((delta.abs() / step.max(1e-3)).ceil() as usize).clamp(1, 1 << MAX_DEPTH);
+
+    for i in 1..count {
+        let t = start + delta * i as f32 / count as f32;
+        let (x, y) = (rx * t.cos(), ry * t.sin());
+        out.push((cos * x - sin * y + center.0, sin * x + cos * y + center.1));
+    }
+    out.push(to);
+}
+
+// the area between pairs of edges, worked out one horizontal band at a time. bands split at
+// every point and every crossing so within a band the edges never change order
+fn fill(v: &mut Vec<f32>, contours: &[Vec<Point>], rule: FillRule) {
+    // (top, bottom, winding) with top having the smaller y
+    let mut edges: Vec<(Point, Point, i32)> = Vec::new();
+    for contour in contours {
+        for i in 0..contour.len() {
+            let (a, b) = (contour[i], contour[(i + 1) % contour.len()]);
+            if a.1 < b.1 {
+                edges.push((a, b, 1));
+            } else if a.1 > b.1 {
+                edges.push((b, a, -1));
+            }
+        }
+    }
+
+    let x_at = |e: &(Point, Point, i32), y: f32| {
+        let (a, b) = (e.0, e.1);
+        a.0 + (b.0 - a.0) * (y - a.1) / (b.1 - a.1)
+    };
+
+    let mut ys: Vec<f32> = edges.iter().flat_map(|e| [e.0 .1, e.1 .1]).collect();
+    for i in 0..edges.len() {
+        for j in i + 1..edges.len() {
+            if let Some(y) = crossing_y(&edges[i], &edges[j]) {
+                ys.push(y);
+            }
+        }
+    }
+    ys.sort_by(f32::total_cmp);
+    ys.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+
+    for band in ys.windows(2) {
+        let (y0, y1) = (band[0], band[1]);
+        let mid = (y0 + y1) / 2.;
+
+        let mut crossing: Vec<&(Point, Point, i32)> = edges
+            .iter()
+            .filter(|e| e.0 .1 <= mid && e.1 .1 > mid)
+            .collect();
+        crossing.sort_by(|a, b| x_at(a, mid).total_cmp(&x_at(b, mid)));
+
+        let mut winding = 0;
+        for pair in crossing.windows(2) {
+            winding += pair[0].2;
+            let inside = match rule {
+                FillRule::NonZero => winding != 0,
+                FillRule::EvenOdd => winding % 2 != 0,
+            };
+            if !inside {
+                continue;
+            }
+
+            let (l0, l1) = ((x_at(pair[0], y0), y0), (x_at(pair[0], y1), y1));
+            let (r0, r1) = ((x_at(pair[1], y0), y0), (x_at(pair[1], y1), y1));
+            if r0.0 - l0.0 > 1e-6 {
+                push_triangle(v, l0, r0, l1);
+            }
+            if r1.0 - l1.0 > 1e-6 {
+                push_triangle(v, r0, r1, l1);
+            }
+        }
+    }
+}
+
+// where two edges cross, if they do somewhere other than their ends
+fn crossing_y(e: &(Point, Point, i32), f: &(Point, Point, i32)) -> Option<f32> {
+    let (p, r) = (e.0, (e.1 .0 - e.0 .0, e.1 .1 - e.0 .1));
+    let (q, s) = (f.0, (f.1 .0 - f.0 .0, f.1 .1 - f.0 .1));
+
+    let denom = r.0 * s.1 - r.1 * s.0;
+    if denom.abs() < 1e-12 {
+        return None;
+    }
+    let t = ((q.0 - p.0) * s.1 - (q.1 - p.1) * s.0) / denom;
+    let u = ((q.0 - p.0) * r.1 - (q.1 - p.1) * r.0) / denom;
+    if t > 0. && t < 1. && u > 0. && u < 1. {
+        Some(p.1 + r.1 * t)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+fn fill_area(path: &Path) -> f32 {
+    path.get_arrays()
+        .chunks(6)
+        .map(|t| ((t[2] - t[0]) * (t[5] - t[1]) - (t[4] - t[0]) * (t[3] - t[1])).abs() / 2.)
+        .sum()
+}
+
+#[cfg(test)]
+fn square_path(path: &mut Path, x: f32, y: f32, size: f32, clockwise: bool) {
+    let mut corners = [
+        vec2(x, y),
+        vec2(x + size, y),
+        vec2(x + size, y + size),
+        vec2(x, y + size),
+    ];
+    if clockwise {
+        corners.reverse();
+    }
+
+    path.move_to(corners[0]);
+    for corner in &corners[1..] {
+        path.line_to(*corner);
+    }
+    path.close();
+}
+
+#[test]
+fn test_fill_rules() {
+    // two squares the same way round, the inner one is wound twice
+    let mut path = Path::new();
+    square_path(&mut path, 0., 0., 4., false);
+    square_path(&mut path, 1., 1., 2., false);
+    assert!((fill_area(&path) - 16.).abs() < 1e-4);
+
+    path.fill_rule = FillRule::EvenOdd;
+    assert!((fill_area(&path) - 12.).abs() < 1e-4);
+
+    // opposite windings cancel out, so non zero cuts a hole too
+    let mut path = Path::new();
+    square_path(&mut path, 0., 0., 4., false);
+    square_path(&mut path, 1., 1., 2., true);
+    assert!((fill_area(&path) - 12.).abs() < 1e-4);
+
+    // a pentagram, even odd leaves the middle pentagon empty
+    let mut star = Path::new();
+    for i in 0..5 {
+        let angle = TAU / 4. + i as f32 * 2. * TAU / 5.;
+        let p = vec2(angle.cos(), angle.sin());
+        if i == 0 {
+            star.move_to(p);
+        } else {
+            star.line_to(p);
+        }
+    }
+    star.close();
+
+    let non_zero = fill_area(&star);
+    star.fill_rule = FillRule::EvenOdd;
+    let even_odd = fill_area(&star);
+    // the inner pentagon has a circumradius of about 0.382
+    let pentagon = 5. / 2. * 0.381966f32.powi(2) * (TAU / 5.).sin();
+    assert!((non_zero - even_odd - pentagon).abs() < 1e-3);
+}
+
+#[test]
+fn test_curves() {
+    use std::f32::consts::PI;
+
+    // a circle out of two half turns
+    let mut circle = Path::new();
+    circle.tolerance = 0.001;
+    circle.move_to(vec2(1., 0.));
+    circle.arc_to(vec2(1., 1.), 0., false, true, vec2(-1., 0.));
+    circle.arc_to(vec2(1., 1.), 0., false, true, vec2(1., 0.));
+    circle.close();
+    assert!((fill_area(&circle) - PI).abs() < 0.01);
+
+    // every flattened point of a quad curve lies on it within the tolerance
+    let mut quad = Path::new();
+    quad.tolerance = 0.01;
+    quad.move_to(vec2(0., 0.));
+    quad.quad_to(vec2(1., 2.), vec2(2., 0.));
+    let points = &quad.flatten()[0].points;
+    assert!(points.len() > 4);
+    for p in points {
+        // y = x * (2 - x) for this curve
+        assert!((p[Y] - p[X] * (2. - p[X])).abs() < 0.01);
+    }
+
+    // tighter tolerance, more lines
+    let cubic = |tolerance| {
+        let mut cubic = Path::new();
+        cubic.tolerance = tolerance;
+        cubic.move_to(vec2(0., 0.));
+        cubic.cubic_to(vec2(0., 1.), vec2(1., 1.), vec2(1., 0.));
+        cubic.flatten()[0].points.len()
+    };
+    assert!(cubic(0.001) > cubic(0.1));
+}
+
+#[test]
+fn test_sub_paths() {
+    let mut path = Path::new();
+    path.move_to(vec2(0., 0.));
+    path.line_to(vec2(1., 0.));
+    path.move_to(vec2(5., 5.));
+    path.line_to(vec2(6., 5.));
+    path.line_to(vec2(6., 6.));
+    path.close();
+    // carries on from where the closed path started
+    path.line_to(vec2(0., 9.));
+
+    let contours = path.flatten();
+    assert_eq!(contours.len(), 3);
+    assert!(!contours[0].closed);
+    assert!(contours[1].closed);
+    assert_eq!(contours[2].points[0][X], 5.);
+}
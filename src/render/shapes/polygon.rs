@@ -0,0 +1,488 @@
+use std::fmt::Display;
+
+use crate::math::vector::*;
+use crate::render::ogl::{Vao, Vbo, VertexPosInfo2D};
+
+use super::Shape;
+
+// anything closer than this is treated as the same point or a straight line
+const EPSILON: f64 = 1e-9;
+
+type P = (f64, f64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolygonError {
+    TooFewPoints,
+    ZeroArea,
+    // edges are numbered by the point they start at
+    SelfIntersecting(usize, usize),
+    // a hole that is not completely inside the outline
+    HoleOutside(usize),
+    // a hole that touches the outline or another hole
+    HoleOverlaps(usize),
+}
+
+impl Display for PolygonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolygonError::TooFewPoints => write!(f, "A polygon needs at least 3 points"),
+            PolygonError::ZeroArea => write!(f, "The polygon has no area"),
+            PolygonError::SelfIntersecting(a, b) => {
+                write!(f, "Polygon edges {a} and {b} cross each other")
+            }
+            PolygonError::HoleOutside(i) => write!(f, "Hole {i} is not inside the polygon"),
+            PolygonError::HoleOverlaps(i) => {
+                write!(f, "Hole {i} touches the outline or another hole")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolygonError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+// with y up, y down flips it
+pub fn winding(points: &[Vec2]) -> Winding {
+    if signed_area(&to_points(points)) < 0. {
+        Winding::Clockwise
+    } else {
+        Winding::CounterClockwise
+    }
+}
+
+// any simple outline, concave is fine, with any number of holes in it.
+// the points are checked when they are added so triangulating can't fail
+pub struct Polygon {
+    outer: Vec<Vec2>,
+    holes: Vec<Vec<Vec2>>,
+}
+
+impl Polygon {
+    // either winding works, the outline is stored counter clockwise
+    pub fn new(points: &[Vec2]) -> Result<Self, PolygonError> {
+        let outer = clean(points, Winding::CounterClockwise)?;
+        let p = to_points(&outer);
+
+        let edges = edges_of(&p);
+        for i in 0..edges.len() {
+            for j in i + 1..edges.len() {
+                let adjacent = j == i + 1 || (i == 0 && j == edges.len() - 1);
+                if !adjacent && segments_touch(edges[i], edges[j]) {
+                    return Err(PolygonError::SelfIntersecting(i, j));
+                }
+            }
+        }
+
+        Ok(Self {
+            outer,
+            holes: Vec::new(),
+        })
+    }
+
+    pub fn with_hole(mut self, points: &[Vec2]) -> Result<Self, PolygonError> {
+        self.add_hole(points)?;
+        Ok(self)
+    }
+
+    // holes are stored clockwise, they can't touch the outline or each other
+    pub fn add_hole(&mut self, points: &[Vec2]) -> Result<(), PolygonError> {
+        let index = self.holes.len();
+        let hole = clean(points, Winding::Clockwise)?;
+        let p = to_points(&hole);
+
+        // the hole's own edges against each other
+        if Polygon::new(&hole).is_err() {
+            return Err(PolygonError::HoleOverlaps(index));
+        }
+
+        let outer = to_points(&self.outer);
+        let others: Vec<Vec<P>> = self.holes.iter().map(|h| to_points(h)).collect();
+
+        let hole_edges = edges_of(&p);
+        for other in std::iter::once(&outer).chain(&others) {
+            for edge in edges_of(other) {
+                if hole_edges.iter().any(|h| segments_touch(*h, edge)) {
+                    return Err(PolygonError::HoleOverlaps(index));
+                }
+            }
+        }
+
+        // nothing crosses, so one point is enough to tell what is inside what
+        if !point_in_polygon(p[0], &outer) {
+            return Err(PolygonError::HoleOutside(index));
+        }
+        if others
+            .iter()
+            .any(|o| point_in_polygon(p[0], o) || point_in_polygon(o[0], &p))
+        {
+            return Err(PolygonError::HoleOverlaps(index));
+        }
+
+        self.holes.push(hole);
+        Ok(())
+    }
+
+    pub fn outer(&self) -> &[Vec2] {
+        &self.outer
+    }
+
+    pub fn holes(&self) -> &[Vec<Vec2>] {
+        &self.holes
+    }
+
+    pub fn area(&self) -> f32 {
+        let holes: f64 = self.holes.iter().map(|h| signed_area(&to_points(h))).sum();
+        (signed_area(&to_points(&self.outer)) + holes) as f32
+    }
+
+    // counter clockwise triangles covering the polygon
+    pub fn triangulate(&self) -> Vec<[Vec2; 3]> {
+        let mut merged = to_points(&self.outer);
+
+        // each hole gets joined to the outline by a pair of edges going there and back,
+        // rightmost hole first so the bridges never have to cross a hole that isnt merged yet
+        let mut holes: Vec<Vec<P>> = self.holes.iter().map(|h| to_points(h)).collect();
+        holes.sort_by(|a, b| max_x(b).total_cmp(&max_x(a)));
+        for hole in &holes {
+            bridge(&mut merged, hole);
+        }
+
+        ear_clip(&merged)
+            .into_iter()
+            .map(|t| t.map(|i| vec2(merged[i].0 as f32, merged[i].1 as f32)))
+            .collect()
+    }
+}
+
+impl Shape for Polygon {
+    fn get_arrays(&self) -> Vec<f32> {
+        self.triangulate()
+            .iter()
+            .flat_map(|t| t.iter().flat_map(|p| [p[X], p[Y]]))
+            .collect()
+    }
+
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+    }
+}
+
+fn to_points(points: &[Vec2]) -> Vec<P> {
+    points.iter().map(|p| (p[X] as f64, p[Y] as f64)).collect()
+}
+
+// drops repeated points, checks the basics and turns it to the wanted winding
+fn clean(points: &[Vec2], wanted: Winding) -> Result<Vec<Vec2>, PolygonError> {
+    let mut out: Vec<Vec2> = Vec::with_capacity(points.len());
+    for p in points {
+        if out.last().is_none_or(|l| l[X] != p[X] || l[Y] != p[Y]) {
+            out.push(*p);
+        }
+    }
+    while out.len() > 1 && out[0][X] == out[out.len() - 1][X] && out[0][Y] == out[out.len() - 1][Y]
+    {
+        out.pop();
+    }
+
+    if out.len() < 3 {
+        return Err(PolygonError::TooFewPoints);
+    }
+    if signed_area(&to_points(&out)).abs() < EPSILON {
+        return Err(PolygonError::ZeroArea);
+    }
+    if winding(&out) != wanted {
+        out.reverse();
+    }
+    Ok(out)
+}
+
+fn edges_of(p: &[P]) -> Vec<(P, P)> {
+    (0..p.len()).map(|i| (p[i], p[(i + 1) % p.len()])).collect()
+}
+
+fn signed_area(p: &[P]) -> f64 {
+    edges_of(p)
+        .iter()
+        .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+        .sum::<f64>()
+        / 2.
+}
+
+fn max_x(p: &[P]) -> f64 {
+    p.iter().map(|p| p.0).fold(f64::MIN, f64::max)
+}
+
+// positive when a, b, c turn left
+fn cross(a: P, b: P, c: P) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+fn on_segment(p: P, (a, b): (P, P)) -> bool {
+    cross(a, b, p).abs() < EPSILON
+        && p.0 >= a.0.min(b.0) - EPSILON
+        && p.0 <= a.0.max(b.0) + EPSILON
+        && p.1 >= a.1.min(b.1) - EPSILON
+        && p.1 <= a.1.max(b.1) + EPSILON
+}
+
+// true if they cross or even just touch
+fn segments_touch(s: (P, P), t: (P, P)) -> bool {
+    let d1 = cross(t.0, t.1, s.0);
+    let d2 = cross(t.0, t.1, s.1);
+    let d3 = cross(s.0, s.1, t.0);
+    let d4 = cross(s.0, s.1, t.1);
+
+    if ((d1 > EPSILON && d2 < -EPSILON) || (d1 < -EPSILON && d2 > EPSILON))
+        && ((d3 > EPSILON && d4 < -EPSILON) || (d3 < -EPSILON && d4 > EPSILON))
+    {
+        return true;
+    }
+
+    on_segment(s.0, t) || on_segment(s.1, t) || on_segment(t.0, s) || on_segment(t.1, s)
+}
+
+fn point_in_polygon(p: P, poly: &[P]) -> bool {
+    let mut inside = false;
+    for (a, b) in edges_of(poly) {
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) * (b.0 - a.0) / (b.1 - a.1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// inside or on the edge of a counter clockwise triangle
+fn point_in_triangle(p: P, a: P, b: P, c: P) -> bool {
+    cross(a, b, p) >= -EPSILON && cross(b, c, p) >= -EPSILON && cross(c, a, p) >= -EPSILON
+}
+
+// splices a clockwise hole into the counter clockwise outline through a pair of
+// bridge edges, following Eberly's "Triangulation by Ear Clipping"
+fn bridge(outer: &mut Vec<P>, hole: &[P]) {
+    let m_index = (0..hole.len())
+        .max_by(|&a, &b| hole[a].0.total_cmp(&hole[b].0))
+        .unwrap();
+    let m = hole[m_index];
+
+    // the closest outline edge hit by a ray from m going right, only edges going up
+    // have the inside facing m, which also picks the right side of earlier bridges
+    let n = outer.len();
+    let mut best: Option<(f64, usize)> = None;
+    for i in 0..n {
+        let (a, b) = (outer[i], outer[(i + 1) % n]);
+        if !(a.1 <= m.1 && b.1 > m.1) {
+            continue;
+        }
+        let x = a.0 + (m.1 - a.1) * (b.0 - a.0) / (b.1 - a.1);
+        if x >= m.0 - EPSILON && best.is_none_or(|(bx, _)| x < bx) {
+            best = Some((x, i));
+        }
+    }
+    let (hit_x, edge) = best.expect("A valid hole always has the outline to its right");
+    let hit = (hit_x, m.1);
+
+    // the end of that edge furthest right is visible from m unless a reflex corner of the
+    // outline is in the way, then the one of those closest in angle to the ray is
+    let (a, b) = (edge, (edge + 1) % n);
+    let mut p = if outer[a].0 > outer[b].0 { a } else { b };
+
+    if (outer[p].0 - hit.0).abs() > EPSILON || (outer[p].1 - hit.1).abs() > EPSILON {
+        let (t0, t1, t2) = if cross(m, hit, outer[p]) > 0. {
+            (m, hit, outer[p])
+        } else {
+            (m, outer[p], hit)
+        };
+
+        let mut best_angle = f64::MAX;
+        for r in 0..n {
+            let (prev, next) = (outer[(r + n - 1) % n], outer[(r + 1) % n]);
+            let q = outer[r];
+            let at_p = (q.0 - outer[p].0).abs() < EPSILON && (q.1 - outer[p].1).abs() < EPSILON;
+            if at_p || cross(prev, q, next) > 0. || !point_in_triangle(q, t0, t1, t2) {
+                continue;
+            }
+            let (dx, dy) = (q.0 - m.0, q.1 - m.1);
+            let angle = dy.abs().atan2(dx);
+            if angle < best_angle {
+                best_angle = angle;
+                p = r;
+            }
+        }
+    }
+
+    let mut merged = Vec::with_capacity(n + hole.len() + 2);
+    merged.extend_from_slice(&outer[..=p]);
+    merged.extend_from_slice(&hole[m_index..]);
+    merged.extend_from_slice(&hole[..=m_index]);
+    merged.extend_from_slice(&outer[p..]);
+    *outer = merged;
+}
+
+// cuts off one convex corner with nothing else inside it at a time
+fn ear_clip(points: &[P]) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len().saturating_sub(2));
+
+    let same = |a: P, b: P| (a.0 - b.0).abs() < EPSILON && (a.1 - b.1).abs() < EPSILON;
+
+    let mut i = 0;
+    let mut misses = 0;
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        i %= count;
+        let (ia, ib, ic) = (
+            remaining[(i + count - 1) % count],
+            remaining[i],
+            remaining[(i + 1) % count],
+        );
+        let (a, b, c) = (points[ia], points[ib], points[ic]);
+        let turn = cross(a, b, c);
+
+        // straight through or a bridge doubling back, nothing to fill
+        if turn.abs() < EPSILON {
+            remaining.remove(i);
+            misses = 0;
+            continue;
+        }
+
+        let ear = turn > 0.
+            && !remaining.iter().any(|&j| {
+                let q = points[j];
+                !same(q, a) && !same(q, b) && !same(q, c) && point_in_triangle(q, a, b, c)
+            });
+
+        // floating point can leave no clean ear on nasty input, cut something anyway
+        if ear || misses > count {
+            if turn > 0. {
+                triangles.push([ia, ib, ic]);
+            }
+            remaining.remove(i);
+            misses = 0;
+        } else {
+            i += 1;
+            misses += 1;
+        }
+    }
+
+    if remaining.len() == 3 {
+        let [a, b, c] = [remaining[0], remaining[1], remaining[2]];
+        if cross(points[a], points[b], points[c]) > EPSILON {
+            triangles.push([a, b, c]);
+        }
+    }
+
+    triangles
+}
+
+#[cfg(test)]
+fn triangle_area(triangles: &[[Vec2; 3]]) -> f32 {
+    triangles
+        .iter()
+        .map(|[a, b, c]| {
+            let area = ((b[X] - a[X]) * (c[Y] - a[Y]) - (b[Y] - a[Y]) * (c[X] - a[X])) / 2.;
+            // every triangle comes out counter clockwise
+            assert!(area > 0.);
+            area
+        })
+        .sum()
+}
+
+#[cfg(test)]
+fn square(x: f32, y: f32, size: f32) -> Vec<Vec2> {
+    vec![
+        vec2(x, y),
+        vec2(x + size, y),
+        vec2(x + size, y + size),
+        vec2(x, y + size),
+    ]
+}
+
+#[test]
+fn test_concave() {
+    // an L, given clockwise
+    let l = Polygon::new(&[
+        vec2(0., 0.),
+        vec2(0., 3.),
+        vec2(1., 3.),
+        vec2(1., 1.),
+        vec2(3., 1.),
+        vec2(3., 0.),
+    ])
+    .unwrap();
+    assert_eq!(winding(l.outer()), Winding::CounterClockwise);
+
+    let triangles = l.triangulate();
+    assert_eq!(triangles.len(), 4);
+    assert!((triangle_area(&triangles) - 5.).abs() < 1e-5);
+
+    // a comb with lots of reflex corners
+    let mut comb = vec![vec2(0., 0.), vec2(10., 0.)];
+    for i in (0..5).rev() {
+        let x = i as f32 * 2.;
+        comb.extend([
+            vec2(x + 2., 5.),
+            vec2(x + 1., 5.),
+            vec2(x + 1., 1.),
+            vec2(x, 1.),
+        ]);
+    }
+    let comb = Polygon::new(&comb).unwrap();
+    assert!((triangle_area(&comb.triangulate()) - comb.area()).abs() < 1e-4);
+    assert!((comb.area() - 30.).abs() < 1e-4);
+}
+
+#[test]
+fn test_holes() {
+    let donut = Polygon::new(&square(0., 0., 4.))
+        .unwrap()
+        .with_hole(&square(1., 1., 2.))
+        .unwrap();
+    assert_eq!(donut.area(), 12.);
+    assert!((triangle_area(&donut.triangulate()) - 12.).abs() < 1e-5);
+
+    let two = Polygon::new(&square(0., 0., 10.))
+        .unwrap()
+        .with_hole(&square(1., 1., 2.))
+        .unwrap()
+        .with_hole(&square(5., 6., 3.))
+        .unwrap();
+    assert!((triangle_area(&two.triangulate()) - (100. - 4. - 9.)).abs() < 1e-4);
+}
+
+#[test]
+fn test_validation() {
+    assert_eq!(
+        Polygon::new(&[vec2(0., 0.), vec2(1., 1.)]).err(),
+        Some(PolygonError::TooFewPoints)
+    );
+    assert_eq!(
+        Polygon::new(&[vec2(0., 0.), vec2(1., 1.), vec2(2., 2.)]).err(),
+        Some(PolygonError::ZeroArea)
+    );
+
+    let bowtie = [vec2(0., 0.), vec2(2., 2.), vec2(2., 0.), vec2(0., 3.)];
+    assert!(matches!(
+        Polygon::new(&bowtie),
+        Err(PolygonError::SelfIntersecting(..))
+    ));
+
+    let mut polygon = Polygon::new(&square(0., 0., 4.)).unwrap();
+    assert_eq!(
+        polygon.add_hole(&square(5., 5., 1.)),
+        Err(PolygonError::HoleOutside(0))
+    );
+    assert_eq!(
+        polygon.add_hole(&square(3., 3., 2.)),
+        Err(PolygonError::HoleOverlaps(0))
+    );
+    polygon.add_hole(&square(1., 1., 1.)).unwrap();
+    assert_eq!(
+        polygon.add_hole(&square(1.5, 1.5, 1.)),
+        Err(PolygonError::HoleOverlaps(1))
+    );
+}
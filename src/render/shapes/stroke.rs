@@ -0,0 +1,676 @@
+use std::f32::consts::TAU;
+
+use crate::math::vector::*;
+use crate::render::ogl::{Vao, Vbo, VertexPosInfo2D};
+
+use super::polygon::Polygon;
+use super::{
+    arc_points, push_triangle, segments_for, Arc, Circle, Ellipse, Line, Pie, Point, Polyline,
+    RegularPolygon, Ring, RoundedRect, Shape, Square, Star, Triangle, DEFAULT_PIXELS_PER_UNIT,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineJoin {
+    // sharp corners, falls back to bevel past the miter limit
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineCap {
+    // stops right at the end point
+    Butt,
+    Round,
+    // like butt but half the width further out
+    Square,
+}
+
+// on and off lengths repeating along the line, offset moves where the pattern starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dash {
+    pub pattern: Vec<f32>,
+    pub offset: f32,
+}
+
+impl Dash {
+    pub fn new(on: f32, off: f32) -> Self {
+        Self {
+            pattern: vec![on, off],
+            offset: 0.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrokeStyle {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    // how far a miter can reach past the corner, in half widths
+    pub miter_limit: f32,
+    pub dash: Option<Dash>,
+    // for round joins and caps, per whole turn
+    pub segments: usize,
+}
+
+impl StrokeStyle {
+    // round joins and caps fit the default camera like Circle::new does
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.,
+            dash: None,
+            segments: 0,
+        }
+        .fit_to_screen(DEFAULT_PIXELS_PER_UNIT)
+    }
+
+    // a round join or cap is part of a circle as wide as the line
+    pub fn fit_to_screen(mut self, pixels_per_unit: f32) -> Self {
+        self.segments = segments_for(self.width / 2. * pixels_per_unit);
+        self
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    pub fn with_dash(mut self, dash: Dash) -> Self {
+        self.dash = Some(dash);
+        self
+    }
+}
+
+// one connected run of points, closed ones join the last point back to the first
+#[derive(Debug, Clone)]
+pub struct Contour {
+    pub points: Vec<Vec2>,
+    pub closed: bool,
+}
+
+impl Contour {
+    pub fn open(points: Vec<Vec2>) -> Self {
+        Self {
+            points,
+            closed: false,
+        }
+    }
+
+    pub fn closed(points: Vec<Vec2>) -> Self {
+        Self {
+            points,
+            closed: true,
+        }
+    }
+}
+
+// the edges of a shape as lines, what gets stroked for an outlined version of it
+pub trait Outline {
+    fn outline(&self) -> Vec<Contour>;
+}
+
+// the stroke of some contours as a filled triangle list, draws like any other shape
+pub struct Stroke {
+    pub contours: Vec<Contour>,
+    pub style: StrokeStyle,
+}
+
+impl Stroke {
+    pub fn new(contours: Vec<Contour>, style: StrokeStyle) -> Self {
+        Self { contours, style }
+    }
+
+    pub fn polyline(points: Vec<Vec2>, style: StrokeStyle) -> Self {
+        Self::new(vec![Contour::open(points)], style)
+    }
+
+    pub fn outline_of<S: Outline>(shape: &S, style: StrokeStyle) -> Self {
+        Self::new(shape.outline(), style)
+    }
+}
+
+impl Shape for Stroke {
+    fn get_arrays(&self) -> Vec<f32> {
+        let mut v = Vec::new();
+        for contour in &self.contours {
+            let points: Vec<Point> = contour.points.iter().map(|p| (p[X], p[Y])).collect();
+            match &self.style.dash {
+                Some(dash) => {
+                    for (piece, d) in dashes(&points, contour.closed, dash) {
+                        if piece.iter().all(|p| length(sub(*p, piece[0])) < 1e-6) {
+                            // a 0 in the pattern, just the caps so dotted lines show up
+                            push_cap(&mut v, piece[0], d, &self.style);
+                            push_cap(&mut v, piece[0], (-d.0, -d.1), &self.style);
+                        } else {
+                            stroke_points(&mut v, &piece, false, &self.style);
+                        }
+                    }
+                }
+                None => stroke_points(&mut v, &points, contour.closed, &self.style),
+            }
+        }
+        v
+    }
+
+    fn set_attributes(&mut self, vbo: &Vbo, vao: &mut Vao) {
+        vao.add_attribute(vbo, &VertexPosInfo2D);
+    }
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn offset(p: Point, n: Point, by: f32) -> Point {
+    (p.0 + n.0 * by, p.1 + n.1 * by)
+}
+
+fn length(d: Point) -> f32 {
+    (d.0 * d.0 + d.1 * d.1).sqrt()
+}
+
+fn direction(a: Point, b: Point) -> Point {
+    let d = sub(b, a);
+    let len = length(d);
+    (d.0 / len, d.1 / len)
+}
+
+// left of the direction of travel
+fn normal(d: Point) -> Point {
+    (-d.1, d.0)
+}
+
+// triangles from center around the arc between two offsets of the same length
+fn push_round(
+    v: &mut Vec<f32>,
+    center: Point,
+    from: Point,
+    sweep: f32,
+    radius: f32,
+    segments: usize,
+) {
+    let start = from.1.atan2(from.0);
+    let count = ((segments as f32 * sweep.abs() / TAU).ceil() as usize).max(1);
+
+    let mut last = offset(center, from, radius);
+    for i in 1..=count {
+        let angle = start + sweep * i as f32 / count as f32;
+        let next = offset(center, (angle.cos(), angle.sin()), radius);
+        push_triangle(v, center, last, next);
+        last = next;
+    }
+}
+
+fn stroke_points(v: &mut Vec<f32>, points: &[Point], closed: bool, style: &StrokeStyle) {
+    let mut points: Vec<Point> = points.to_vec();
+    points.dedup_by(|a, b| length(sub(*a, *b)) < 1e-6);
+    if closed && points.len() > 1 && length(sub(points[0], points[points.len() - 1])) < 1e-6 {
+        points.pop();
+    }
+
+    let half = style.width / 2.;
+    let n = points.len();
+    if n < 2 || half <= 0. {
+        return;
+    }
+
+    let segment_count = if closed { n } else { n - 1 };
+    let segment = |i: usize| (points[i], points[(i + 1) % n]);
+
+    // every segment is its own quad, the joins fill the gap on the outside of each corner
+    for i in 0..segment_count {
+        let (a, b) = segment(i);
+        let side = normal(direction(a, b));
+        let (a0, a1) = (offset(a, side, half), offset(a, side, -half));
+        let (b0, b1) = (offset(b, side, half), offset(b, side, -half));
+        push_triangle(v, a0, a1, b1);
+        push_triangle(v, a0, b1, b0);
+    }
+
+    let joins = if closed { 0..n } else { 1..n - 1 };
+    for i in joins {
+        let p = points[i];
+        let d0 = direction(points[(i + n - 1) % n], p);
+        let d1 = direction(p, points[(i + 1) % n]);
+
+        let turn = d0.0 * d1.1 - d0.1 * d1.0;
+        if turn.abs() < 1e-6 && d0.0 * d1.0 + d0.1 * d1.1 > 0. {
+            continue;
+        }
+
+        // the outside of the corner is on the right of a left turn and the other way around
+        let outside = if turn > 0. { -1. } else { 1. };
+        let (n0, n1) = (normal(d0), normal(d1));
+        let (n0, n1) = (
+            (n0.0 * outside, n0.1 * outside),
+            (n1.0 * outside, n1.1 * outside),
+        );
+        let (e0, e1) = (offset(p, n0, half), offset(p, n1, half));
+
+        match style.join {
+            LineJoin::Round => {
+                let sweep = (n0.0 * n1.1 - n0.1 * n1.0).atan2(n0.0 * n1.0 + n0.1 * n1.1);
+                push_round(v, p, n0, sweep, half, style.segments);
+            }
+            LineJoin::Miter | LineJoin::Bevel => {
+                let mid = (n0.0 + n1.0, n0.1 + n1.1);
+                let mid_len = length(mid);
+                // 1 / cos of half the angle between the normals
+                let reach = if mid_len > 1e-6 {
+                    2. / mid_len
+                } else {
+                    f32::MAX
+                };
+
+                if style.join == LineJoin::Miter && reach <= style.miter_limit {
+                    let tip = offset(p, (mid.0 / mid_len, mid.1 / mid_len), half * reach);
+                    push_triangle(v, p, e0, tip);
+                    push_triangle(v, p, tip, e1);
+                } else {
+                    push_triangle(v, p, e0, e1);
+                }
+            }
+        }
+    }
+
+    if !closed {
+        let ends = [
+            (points[0], direction(points[1], points[0])),
+            (points[n - 1], direction(points[n - 2], points[n - 1])),
+        ];
+        for (p, out) in ends {
+            push_cap(v, p, out, style);
+        }
+    }
+}
+
+// the end of a line at p, out points away from the line
+fn push_cap(v: &mut Vec<f32>, p: Point, out: Point, style: &StrokeStyle) {
+    let half = style.width / 2.;
+    if half <= 0. {
+        return;
+    }
+
+    let side = normal(out);
+    match style.cap {
+        LineCap::Butt => {}
+        LineCap::Square => {
+            let (a0, a1) = (offset(p, side, half), offset(p, side, -half));
+            let (b0, b1) = (offset(a0, out, half), offset(a1, out, half));
+            push_triangle(v, a0, b0, b1);
+            push_triangle(v, a0, b1, a1);
+        }
+        LineCap::Round => {
+            push_round(v, p, side, -TAU / 2., half, style.segments);
+        }
+    }
+}
+
+// splits the line into the on parts of the dash pattern, each with the direction the line
+// goes where it ends so a piece with no length still knows which way its caps face
+fn dashes(points: &[Point], closed: bool, dash: &Dash) -> Vec<(Vec<Point>, Point)> {
+    let total: f32 = dash.pattern.iter().sum();
+    if dash.pattern.is_empty() || total <= 0. || dash.pattern.iter().any(|d| *d < 0.) {
+        return vec![(points.to_vec(), (1., 0.))];
+    }
+
+    let mut path = points.to_vec();
+    if closed && !points.is_empty() {
+        path.push(points[0]);
+    }
+
+    // find where in the pattern the line starts
+    let mut index = 0;
+    let mut left = dash.pattern[0];
+    let mut skip = dash.offset.rem_euclid(total);
+    while skip > 0. {
+        if skip >= left {
+            skip -= left;
+            index = (index + 1) % dash.pattern.len();
+            left = dash.pattern[index];
+        } else {
+            left -= skip;
+            skip = 0.;
+        }
+    }
+
+    let mut pieces = Vec::new();
+    let mut current: Vec<Point> = Vec::new();
+    let mut d = (1., 0.);
+    if index % 2 == 0 && !path.is_empty() {
+        current.push(path[0]);
+    }
+
+    for pair in path.windows(2) {
+        let (mut a, b) = (pair[0], pair[1]);
+        let mut remaining = length(sub(b, a));
+        if remaining > 0. {
+            d = direction(a, b);
+        }
+
+        while remaining > 0. {
+            let on = index % 2 == 0;
+            if left > remaining {
+                left -= remaining;
+                if on {
+                    current.push(b);
+                }
+                break;
+            }
+
+            let cut = offset(a, d, left);
+            remaining -= left;
+            a = cut;
+
+            if on {
+                current.push(cut);
+                pieces.push((std::mem::take(&mut current), d));
+            } else {
+                current.push(cut);
+            }
+
+            index = (index + 1) % dash.pattern.len();
+            left = dash.pattern[index];
+        }
+    }
+
+    if current.len() > 1 {
+        pieces.push((current, d));
+    }
+    pieces.retain(|(p, _)| p.len() > 1);
+    pieces
+}
+
+fn contour_of(points: Vec<Point>, closed: bool) -> Contour {
+    Contour {
+        points: points.into_iter().map(|(x, y)| vec2(x, y)).collect(),
+        closed,
+    }
+}
+
+// a full turn of arc points has the first point again at the end
+fn ring_of(center: Vec2, radii: Point, segments: usize) -> Contour {
+    let mut points = arc_points(center, radii, 0., TAU, segments);
+    points.pop();
+    contour_of(points, true)
+}
+
+impl Outline for Square {
+    fn outline(&self) -> Vec<Contour> {
+        let (x, y, w, h) = (self.pos[X], self.pos[Y], self.size[X], self.size[Y]);
+        vec![contour_of(
+            vec![(x, y), (x + w, y), (x + w, y + h), (x, y + h)],
+            true,
+        )]
+    }
+}
+
+impl Outline for Triangle {
+    fn outline(&self) -> Vec<Contour> {
+        vec![Contour::closed(vec![self.pos1, self.pos2, self.pos3])]
+    }
+}
+
+impl Outline for Circle {
+    fn outline(&self) -> Vec<Contour> {
+        vec![ring_of(self.pos, (self.radius, self.radius), self.segments)]
+    }
+}
+
+impl Outline for Ellipse {
+    fn outline(&self) -> Vec<Contour> {
+        vec![ring_of(
+            self.pos,
+            (self.radii[X], self.radii[Y]),
+            self.segments,
+        )]
+    }
+}
+
+impl Outline for RoundedRect {
+    fn outline(&self) -> Vec<Contour> {
+        vec![contour_of(self.rim(), true)]
+    }
+}
+
+impl Outline for RegularPolygon {
+    fn outline(&self) -> Vec<Contour> {
+        let r = self.radius;
+        let mut points = arc_points(
+            self.pos,
+            (r, r),
+            self.rotation,
+            self.rotation + TAU,
+            self.sides,
+        );
+        points.pop();
+        vec![contour_of(points, true)]
+    }
+}
+
+impl Outline for Star {
+    fn outline(&self) -> Vec<Contour> {
+        let mut points = self.rim();
+        points.pop();
+        vec![contour_of(points, true)]
+    }
+}
+
+impl Outline for Arc {
+    fn outline(&self) -> Vec<Contour> {
+        let inner = (self.radius - self.thickness / 2.).max(0.);
+        let outer = self.radius + self.thickness / 2.;
+
+        let mut points = arc_points(
+            self.pos,
+            (outer, outer),
+            self.start,
+            self.end,
+            self.segments,
+        );
+        points.extend(
+            arc_points(
+                self.pos,
+                (inner, inner),
+                self.start,
+                self.end,
+                self.segments,
+            )
+            .into_iter()
+            .rev(),
+        );
+        vec![contour_of(points, true)]
+    }
+}
+
+impl Outline for Pie {
+    fn outline(&self) -> Vec<Contour> {
+        let r = self.radius;
+        let mut points = vec![(self.pos[X], self.pos[Y])];
+        points.extend(arc_points(
+            self.pos,
+            (r, r),
+            self.start,
+            self.end,
+            self.segments,
+        ));
+        vec![contour_of(points, true)]
+    }
+}
+
+impl Outline for Ring {
+    fn outline(&self) -> Vec<Contour> {
+        let (r0, r1) = (self.inner_radius, self.outer_radius);
+        vec![
+            ring_of(self.pos, (r1, r1), self.segments),
+            ring_of(self.pos, (r0, r0), self.segments),
+        ]
+    }
+}
+
+impl Outline for Line {
+    fn outline(&self) -> Vec<Contour> {
+        vec![Contour::open(vec![self.from, self.to])]
+    }
+}
+
+impl Outline for Polyline {
+    fn outline(&self) -> Vec<Contour> {
+        vec![Contour {
+            points: self.points.clone(),
+            closed: self.closed,
+        }]
+    }
+}
+
+impl Outline for Polygon {
+    fn outline(&self) -> Vec<Contour> {
+        std::iter::once(self.outer())
+            .chain(self.holes().iter().map(|h| h.as_slice()))
+            .map(|points| Contour::closed(points.to_vec()))
+            .collect()
+    }
+}
+
+// how much of the grid of sample points in the box is covered by at least one triangle
+#[cfg(test)]
+fn covered_area(v: &[f32], min: Point, max: Point) -> f32 {
+    let inside = |p: Point, t: &[f32]| {
+        let c = |a: Point, b: Point| (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0);
+        let (a, b, d) = ((t[0], t[1]), (t[2], t[3]), (t[4], t[5]));
+        let (c0, c1, c2) = (c(a, b), c(b, d), c(d, a));
+        (c0 >= 0. && c1 >= 0. && c2 >= 0.) || (c0 <= 0. && c1 <= 0. && c2 <= 0.)
+    };
+
+    let step = 0.05;
+    let mut hits = 0;
+    let mut y = min.1 + step / 2.;
+    while y < max.1 {
+        let mut x = min.0 + step / 2.;
+        while x < max.0 {
+            if v.chunks(6).any(|t| inside((x, y), t)) {
+                hits += 1;
+            }
+            x += step;
+        }
+        y += step;
+    }
+    hits as f32 * step * step
+}
+
+#[test]
+fn test_caps() {
+    let line = |cap| {
+        let stroke = Stroke::polyline(
+            vec![vec2(0., 0.), vec2(10., 0.)],
+            StrokeStyle::new(2.).with_cap(cap),
+        );
+        covered_area(&stroke.get_arrays(), (-2., -2.), (12., 2.))
+    };
+
+    assert!((line(LineCap::Butt) - 20.).abs() < 0.1);
+    assert!((line(LineCap::Square) - 24.).abs() < 0.1);
+    assert!((line(LineCap::Round) - (20. + std::f32::consts::PI)).abs() < 0.2);
+}
+
+#[test]
+fn test_joins() {
+    // a square outline 2 wide around a 10 by 10 square fills 12x12 minus the 8x8 middle
+    let square = Square::new(vec2(0., 0.), vec2(10., 10.));
+    let area = |join| {
+        let stroke = Stroke::outline_of(&square, StrokeStyle::new(2.).with_join(join));
+        covered_area(&stroke.get_arrays(), (-2., -2.), (12., 12.))
+    };
+
+    assert!((area(LineJoin::Miter) - 80.).abs() < 0.1);
+    // bevels cut half of each 1x1 outside corner off, round ones leave pi/4 of it
+    assert!((area(LineJoin::Bevel) - 78.).abs() < 0.1);
+    assert!((area(LineJoin::Round) - (76. + std::f32::consts::PI)).abs() < 0.2);
+
+    // past the miter limit a very sharp corner falls back to a bevel
+    let spike = Stroke::polyline(
+        vec![vec2(0., 0.), vec2(10., 0.5), vec2(0., 1.)],
+        StrokeStyle::new(0.5),
+    );
+    let max_x = spike
+        .get_arrays()
+        .chunks(2)
+        .map(|p| p[0])
+        .fold(f32::MIN, f32::max);
+    assert!(max_x < 10.5);
+}
+
+#[test]
+fn test_dashes() {
+    let points = [(0., 0.), (10., 0.)];
+    let pieces: Vec<Vec<Point>> = dashes(&points, false, &Dash::new(2., 1.))
+        .into_iter()
+        .map(|(piece, _)| piece)
+        .collect();
+    assert_eq!(
+        pieces,
+        vec![
+            vec![(0., 0.), (2., 0.)],
+            vec![(3., 0.), (5., 0.)],
+            vec![(6., 0.), (8., 0.)],
+            vec![(9., 0.), (10., 0.)],
+        ]
+    );
+
+    // dashes carry on around corners
+    let corner = [(0., 0.), (3., 0.), (3., 3.)];
+    let mut dash = Dash::new(2., 2.);
+    dash.offset = 1.;
+    assert_eq!(
+        dashes(&corner, false, &dash),
+        vec![
+            (vec![(0., 0.), (1., 0.)], (1., 0.)),
+            (vec![(3., 0.), (3., 2.)], (0., 1.)),
+        ]
+    );
+
+    let stroke = Stroke::polyline(
+        vec![vec2(0., 0.), vec2(10., 0.)],
+        StrokeStyle::new(2.).with_dash(Dash::new(2., 1.)),
+    );
+    assert!((covered_area(&stroke.get_arrays(), (-1., -2.), (11., 2.)) - 14.).abs() < 0.1);
+}
+
+#[test]
+fn test_dotted() {
+    // 0 long dashes are only their caps, a dot every 4 at 0, 4 and 8
+    let dotted = |cap| {
+        let stroke = Stroke::polyline(
+            vec![vec2(0., 0.), vec2(10., 0.)],
+            StrokeStyle::new(2.)
+                .with_cap(cap)
+                .with_dash(Dash::new(0., 4.)),
+        );
+        covered_area(&stroke.get_arrays(), (-2., -2.), (12., 2.))
+    };
+
+    assert_eq!(dotted(LineCap::Butt), 0.);
+    assert!((dotted(LineCap::Square) - 12.).abs() < 0.1);
+    assert!((dotted(LineCap::Round) - 3. * std::f32::consts::PI).abs() < 0.2);
+}
+
+#[test]
+fn test_round_segments() {
+    // a thin line on screen needs a few segments for its round caps, a thick one more
+    let thin = StrokeStyle::new(0.01);
+    let thick = StrokeStyle::new(0.5);
+    assert!(thick.segments > thin.segments);
+    assert_eq!(thick.segments, Circle::new(vec2(0., 0.), 0.25).segments);
+
+    let zoomed = StrokeStyle::new(0.01).fit_to_screen(DEFAULT_PIXELS_PER_UNIT * 4.);
+    assert!(zoomed.segments > thin.segments);
+}
@@ -0,0 +1,379 @@
+use crate::math::color::Color;
+use crate::math::vector::*;
+
+use super::context::{GlContext, SavedBlend};
+use super::ogl::{
+    BufferUsage, Ebo, Material, ShaderBuilder, Vao, Vbo, VertexPosInfo3D, VertexRgbaInfo,
+    VertexUvInfo,
+};
+use super::shaders;
+use super::texture::Texture;
+
+// x, y, depth, u, v, r, g, b, a
+const VERTEX_FLOATS: usize = 9;
+const QUAD_FLOATS: usize = VERTEX_FLOATS * 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    pub u0: f32,
+    pub v0: f32,
+    pub u1: f32,
+    pub v1: f32,
+}
+
+impl UvRect {
+    pub const FULL: UvRect = UvRect {
+        u0: 0.,
+        v0: 0.,
+        u1: 1.,
+        v1: 1.,
+    };
+
+    pub fn new(u0: f32, v0: f32, u1: f32, v1: f32) -> Self {
+        Self { u0, v0, u1, v1 }
+    }
+
+    // a rect in pixels inside a texture that is texture_width by texture_height
+    pub fn from_pixels(
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        texture_width: u32,
+        texture_height: u32,
+    ) -> Self {
+        let (tw, th) = (texture_width as f32, texture_height as f32);
+        Self {
+            u0: x as f32 / tw,
+            v0: y as f32 / th,
+            u1: (x + width) as f32 / tw,
+            v1: (y + height) as f32 / th,
+        }
+    }
+
+    pub fn flip_x(self) -> Self {
+        Self {
+            u0: self.u1,
+            u1: self.u0,
+            ..self
+        }
+    }
+
+    pub fn flip_y(self) -> Self {
+        Self {
+            v0: self.v1,
+            v1: self.v0,
+            ..self
+        }
+    }
+}
+
+impl Default for UvRect {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+// positions and sizes are in pixels from the top left of the screen
+#[derive(Debug, Clone, Copy)]
+pub struct Sprite {
+    pub position: Vec2,
+    pub size: Vec2,
+    // radians, clockwise on screen because y points down
+    pub rotation: f32,
+    // the point that sits on position and that the sprite rotates around, 0,0 is the top left
+    // corner and 1,1 is the bottom right
+    pub origin: Vec2,
+    pub uv: UvRect,
+    pub tint: Color,
+    // 0 is in front, 1 is at the back
+    pub depth: f32,
+}
+
+impl Sprite {
+    pub fn new(position: Vec2, size: Vec2) -> Self {
+        Self {
+            position,
+            size,
+            rotation: 0.,
+            origin: vec2(0., 0.),
+            uv: UvRect::FULL,
+            tint: Color::WHITE,
+            depth: 0.,
+        }
+    }
+
+    // four corners starting at the top left and going clockwise
+    pub fn vertices(&self) -> [f32; QUAD_FLOATS] {
+        let (sin, cos) = self.rotation.sin_cos();
+        let [r, g, b, a] = self.tint.to_rgba_f32();
+        let uv = self.uv;
+
+        let corners = [
+            (0., 0., uv.u0, uv.v0),
+            (1., 0., uv.u1, uv.v0),
+            (1., 1., uv.u1, uv.v1),
+            (0., 1., uv.u0, uv.v1),
+        ];
+
+        let mut out = [0.; QUAD_FLOATS];
+        for (i, (cx, cy, u, v)) in corners.into_iter().enumerate() {
+            let lx = (cx - self.origin[X]) * self.size[X];
+            let ly = (cy - self.origin[Y]) * self.size[Y];
+
+            let x = self.position[X] + lx * cos - ly * sin;
+            let y = self.position[Y] + lx * sin + ly * cos;
+
+            out[i * VERTEX_FLOATS..(i + 1) * VERTEX_FLOATS]
+                .copy_from_slice(&[x, y, self.depth, u, v, r, g, b, a]);
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortMode {
+    // fewest draw calls, use the depth buffer if the order matters
+    Texture,
+    // for alpha blending, far sprites are drawn first
+    BackToFront,
+    FrontToBack,
+    // in the order they were drawn
+    Deferred,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MaterialId(usize);
+
+struct QueuedSprite {
+    material: usize,
+    texture: u32,
+    sprite: Sprite,
+}
+
+// collects sprites between begin and flush, then draws every run of sprites that share a
+// material and texture with one draw call out of a single streamed vertex buffer
+pub struct SpriteBatch {
+    vao: Vao,
+    vbo: Vbo,
+    ebo: Ebo,
+    capacity: usize,
+    materials: Vec<Material>,
+    queue: Vec<QueuedSprite>,
+    vertices: Vec<f32>,
+    screen_size: Vec2,
+    sort: SortMode,
+    draw_calls: usize,
+}
+
+pub fn sprite_material() -> Material {
+    let mut builder = ShaderBuilder::new();
+    builder.add_shader(shaders::SPRITE_VERTEX);
+    builder.add_shader(shaders::SPRITE_FRAGMENT);
+
+    let mut shader = builder.build();
+    shader.set_uniform("sprite", 0i32);
+
+    Material { shader }
+}
+
+impl SpriteBatch {
+    pub const DEFAULT_MATERIAL: MaterialId = MaterialId(0);
+
+    // screen size is what the pixel positions are measured against, update it on resize
+    pub fn new(screen_width: f32, screen_height: f32) -> Self {
+        let mut vao = Vao::new_bind();
+        let ebo = Ebo::new();
+        let vbo = Vbo::with_usage(BufferUsage::Stream);
+
+        vao.add_attribute(&vbo, &VertexPosInfo3D);
+        vao.add_attribute(&vbo, &VertexUvInfo);
+        vao.add_attribute(&vbo, &VertexRgbaInfo);
+
+        let mut batch = Self {
+            vao,
+            vbo,
+            ebo,
+            capacity: 0,
+            materials: vec![sprite_material()],
+            queue: Vec::new(),
+            vertices: Vec::new(),
+            screen_size: vec2(screen_width, screen_height),
+            sort: SortMode::Texture,
+            draw_calls: 0,
+        };
+        batch.reserve(1024);
+        batch
+    }
+
+    // the shader has to take the same vertex layout and uniforms as shaders::SPRITE_VERTEX
+    pub fn add_material(&mut self, material: Material) -> MaterialId {
+        self.materials.push(material);
+        MaterialId(self.materials.len() - 1)
+    }
+
+    pub fn set_sort_mode(&mut self, sort: SortMode) {
+        self.sort = sort;
+    }
+
+    pub fn set_screen_size(&mut self, width: f32, height: f32) {
+        self.screen_size = vec2(width, height);
+    }
+
+    // how many draw calls the last flush took
+    pub fn draw_calls(&self) -> usize {
+        self.draw_calls
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    // the texture has to stay alive until the next flush
+    pub fn draw(&mut self, texture: &Texture, sprite: &Sprite) {
+        self.draw_with(Self::DEFAULT_MATERIAL, texture, sprite);
+    }
+
+    pub fn draw_with(&mut self, material: MaterialId, texture: &Texture, sprite: &Sprite) {
+        self.queue.push(QueuedSprite {
+            material: material.0,
+            texture: texture.id(),
+            sprite: *sprite,
+        });
+    }
+
+    // grows the gpu buffers so at least sprites quads fit without reallocating
+    fn reserve(&mut self, sprites: usize) {
+        if sprites <= self.capacity {
+            return;
+        }
+
+        let capacity = sprites.next_power_of_two();
+
+        let indices = (0..capacity as u32)
+            .flat_map(|q| [0, 1, 2, 2, 3, 0].map(|i| q * 4 + i))
+            .collect::<Vec<u32>>();
+
+        self.vao.bind();
+        self.ebo.set_data(&indices);
+
+        self.vbo.reserve(capacity * QUAD_FLOATS);
+
+        self.capacity = capacity;
+    }
+
+    fn sort_queue(&mut self) {
+        let by_state = |a: &QueuedSprite, b: &QueuedSprite| {
+            (a.material, a.texture).cmp(&(b.material, b.texture))
+        };
+
+        // stable sorts so sprites with the same key keep the order they were drawn in
+        match self.sort {
+            SortMode::Texture => self.queue.sort_by(by_state),
+            SortMode::BackToFront => self.queue.sort_by(|a, b| {
+                b.sprite
+                    .depth
+                    .total_cmp(&a.sprite.depth)
+                    .then_with(|| by_state(a, b))
+            }),
+            SortMode::FrontToBack => self.queue.sort_by(|a, b| {
+                a.sprite
+                    .depth
+                    .total_cmp(&b.sprite.depth)
+                    .then_with(|| by_state(a, b))
+            }),
+            SortMode::Deferred => {}
+        }
+    }
+
+    pub fn flush(&mut self) {
+        self.draw_calls = 0;
+        if self.queue.is_empty() {
+            return;
+        }
+
+        self.sort_queue();
+        self.reserve(self.queue.len());
+
+        self.vertices.clear();
+        for queued in &self.queue {
+            self.vertices.extend_from_slice(&queued.sprite.vertices());
+        }
+
+        self.vao.bind();
+        // orphan the old storage so the driver doesnt wait on last frames draws
+        self.vbo.orphan();
+        self.vbo.set_sub_data(0, &self.vertices);
+
+        let blend = SavedBlend::alpha();
+        let ctx = GlContext::current();
+        let mut start = 0;
+        while start < self.queue.len() {
+            let (material, texture) = (self.queue[start].material, self.queue[start].texture);
+            let end = self.queue[start..]
+                .iter()
+                .position(|q| q.material != material || q.texture != texture)
+                .map_or(self.queue.len(), |p| start + p);
+
+            let mat = &mut self.materials[material];
+            mat.shader.set_uniform("screen_size", self.screen_size);
+            mat.bind();
+
+            ctx.active_texture(0);
+            ctx.bind_texture(gl::TEXTURE_2D, texture);
+            self.ebo
+                .draw_elements(gl::TRIANGLES, start * 6, (end - start) * 6);
+
+            self.draw_calls += 1;
+            start = end;
+        }
+        blend.restore();
+
+        self.queue.clear();
+    }
+}
+
+#[test]
+fn test_sprite_vertices() {
+    let mut sprite = Sprite::new(vec2(10., 20.), vec2(4., 2.));
+    sprite.uv = UvRect::new(0.25, 0.5, 0.75, 1.0);
+
+    let v = sprite.vertices();
+    let corner = |i: usize| (v[i * VERTEX_FLOATS], v[i * VERTEX_FLOATS + 1]);
+    let uv = |i: usize| (v[i * VERTEX_FLOATS + 3], v[i * VERTEX_FLOATS + 4]);
+
+    assert_eq!(corner(0), (10., 20.));
+    assert_eq!(corner(1), (14., 20.));
+    assert_eq!(corner(2), (14., 22.));
+    assert_eq!(corner(3), (10., 22.));
+
+    assert_eq!(uv(0), (0.25, 0.5));
+    assert_eq!(uv(2), (0.75, 1.0));
+
+    // white tint
+    assert_eq!(v[5..9], [1., 1., 1., 1.]);
+}
+
+#[test]
+fn test_sprite_rotation_around_origin() {
+    let mut sprite = Sprite::new(vec2(0., 0.), vec2(2., 2.));
+    sprite.origin = vec2(0.5, 0.5);
+    sprite.rotation = std::f32::consts::FRAC_PI_2;
+
+    let v = sprite.vertices();
+
+    // a quarter turn moves the top left corner to the top right
+    assert!((v[0] - 1.).abs() < 1e-6);
+    assert!((v[1] + 1.).abs() < 1e-6);
+}
+
+#[test]
+fn test_uv_rect_from_pixels() {
+    let uv = UvRect::from_pixels(16, 0, 16, 32, 64, 32);
+    assert_eq!(uv, UvRect::new(0.25, 0., 0.5, 1.));
+    assert_eq!(uv.flip_x(), UvRect::new(0.5, 0., 0.25, 1.));
+}
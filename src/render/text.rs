@@ -0,0 +1,575 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+
+use crate::math::color::Color;
+use crate::math::vector::*;
+
+use super::atlas::SkylinePacker;
+use super::sprite::{Sprite, SpriteBatch, UvRect};
+use super::texture::Texture;
+
+// glyphs are rasterized the first time they are drawn onto pages this big
+const PAGE_SIZE: u32 = 512;
+const GLYPH_PADDING: u32 = 1;
+
+// the public domain 8x13 "fixed" font from xorg, printable ascii packed 16 glyphs to a row
+// at one bit per pixel with the leftmost pixel in the high bit
+static DEBUG_FONT: &[u8] = include_bytes!("text/font_8x13.raw");
+const DEBUG_WIDTH: u32 = 8;
+const DEBUG_HEIGHT: u32 = 13;
+const DEBUG_BASELINE: u32 = 10;
+const DEBUG_COLUMNS: u32 = 16;
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(std::io::Error),
+    Parse(&'static str),
+}
+
+impl Display for FontError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FontError::Io(e) => write!(f, "Failed to read the font: {e}"),
+            FontError::Parse(e) => write!(f, "Failed to parse the font: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for FontError {}
+
+impl From<std::io::Error> for FontError {
+    fn from(e: std::io::Error) -> Self {
+        FontError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// in pixels, descent is how far below the baseline the font goes so it is positive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+}
+
+impl LineMetrics {
+    pub fn height(&self) -> f32 {
+        self.ascent + self.descent + self.line_gap
+    }
+}
+
+enum Source {
+    Outline(fontdue::Font),
+    Bitmap,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Glyph {
+    pub(crate) page: usize,
+    pub(crate) uv: UvRect,
+    // from the pen on the baseline to the top left of the bitmap, in raster pixels
+    pub(crate) offset: (f32, f32),
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+struct GlyphPage {
+    packer: SkylinePacker,
+    image: RgbaImage,
+    texture: Option<Texture>,
+    dirty: bool,
+}
+
+// one glyph of laid out text, x is the pen position and y the baseline, relative to the top left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    pub c: char,
+    pub x: f32,
+    pub y: f32,
+}
+
+pub struct Font {
+    source: Source,
+    // outline glyphs are cached per pixel size, the bitmap font only has the one size
+    glyphs: HashMap<(char, u32), Glyph>,
+    pages: Vec<GlyphPage>,
+    white: Option<Glyph>,
+}
+
+impl Font {
+    // any ttf or otf, glyphs get rasterized on the cpu as they are needed
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, FontError> {
+        let font = fontdue::Font::from_bytes(data, fontdue::FontSettings::default())
+            .map_err(FontError::Parse)?;
+        Ok(Self::with_source(Source::Outline(font)))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, FontError> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    // a small pixel font built into the library, ascii only and best at multiples of 13px
+    pub fn debug() -> Self {
+        Self::with_source(Source::Bitmap)
+    }
+
+    fn with_source(source: Source) -> Self {
+        Self {
+            source,
+            glyphs: HashMap::new(),
+            pages: Vec::new(),
+            white: None,
+        }
+    }
+
+    pub fn is_bitmap(&self) -> bool {
+        matches!(self.source, Source::Bitmap)
+    }
+
+    pub fn line_metrics(&self, size: f32) -> LineMetrics {
+        match &self.source {
+            Source::Outline(font) => match font.horizontal_line_metrics(size) {
+                Some(m) => LineMetrics {
+                    ascent: m.ascent,
+                    descent: -m.descent,
+                    line_gap: m.line_gap,
+                },
+                None => LineMetrics {
+                    ascent: size * 0.8,
+                    descent: size * 0.2,
+                    line_gap: 0.,
+                },
+            },
+            Source::Bitmap => {
+                let scale = size / DEBUG_HEIGHT as f32;
+                LineMetrics {
+                    ascent: DEBUG_BASELINE as f32 * scale,
+                    descent: (DEBUG_HEIGHT - DEBUG_BASELINE) as f32 * scale,
+                    line_gap: 0.,
+                }
+            }
+        }
+    }
+
+    pub fn line_height(&self, size: f32) -> f32 {
+        self.line_metrics(size).height()
+    }
+
+    pub fn advance(&self, c: char, size: f32) -> f32 {
+        match &self.source {
+            Source::Outline(font) => font.metrics(c, size).advance_width,
+            Source::Bitmap => DEBUG_WIDTH as f32 * size / DEBUG_HEIGHT as f32,
+        }
+    }
+
+    // added to the advance between the two, usually negative for pairs like "AV"
+    pub fn kerning(&self, left: char, right: char, size: f32) -> f32 {
+        match &self.source {
+            Source::Outline(font) => font.horizontal_kern(left, right, size).unwrap_or(0.),
+            Source::Bitmap => 0.,
+        }
+    }
+
+    // the width of a single line with kerning, newlines are not handled here
+    pub fn line_width(&self, line: &str, size: f32) -> f32 {
+        let mut width = 0.;
+        let mut prev = None;
+        for c in line.chars() {
+            if let Some(p) = prev {
+                width += self.kerning(p, c, size);
+            }
+            width += self.advance(c, size);
+            prev = Some(c);
+        }
+        width
+    }
+
+    // splits on newlines, then breaks lines at spaces so they fit in the width,
+    // a word longer than the whole width is broken between characters
+    pub fn wrap<'a>(&self, text: &'a str, size: f32, width: Option<f32>) -> Vec<&'a str> {
+        let mut lines = Vec::new();
+
+        for paragraph in text.split('\n') {
+            let Some(width) = width else {
+                lines.push(paragraph);
+                continue;
+            };
+
+            let mut start = 0;
+            let mut last_break = None;
+            let mut x = 0.;
+            let mut prev = None;
+
+            for (i, c) in paragraph.char_indices() {
+                let mut advance = self.advance(c, size);
+                if let Some(p) = prev {
+                    advance += self.kerning(p, c, size);
+                }
+
+                if c != ' ' && x + advance > width && i > start {
+                    let end = last_break.unwrap_or(i);
+                    lines.push(paragraph[start..end].trim_end());
+                    start = end;
+                    last_break = None;
+                    x = self.line_width(&paragraph[start..i + c.len_utf8()], size);
+                } else {
+                    x += advance;
+                }
+
+                if c == ' ' {
+                    last_break = Some(i + 1);
+                }
+                prev = Some(c);
+            }
+
+            lines.push(&paragraph[start..]);
+        }
+
+        lines
+    }
+
+    // where every glyph goes and the size of the whole block, with center and right alignment
+    // inside the wrap width when there is one and around x = 0 when there isn't
+    pub fn layout(
+        &self,
+        text: &str,
+        size: f32,
+        wrap_width: Option<f32>,
+        line_spacing: f32,
+        align: Align,
+    ) -> (Vec<PositionedGlyph>, Vec2) {
+        let metrics = self.line_metrics(size);
+        let line_height = metrics.height() * line_spacing;
+
+        let lines = self.wrap(text, size, wrap_width);
+        let widths: Vec<f32> = lines.iter().map(|l| self.line_width(l, size)).collect();
+        let widest = widths.iter().cloned().fold(0., f32::max);
+
+        let mut glyphs = Vec::new();
+        for (i, (line, width)) in lines.iter().zip(&widths).enumerate() {
+            let room = wrap_width.unwrap_or(0.);
+            let mut x = match align {
+                Align::Left => 0.,
+                Align::Center => (room - width) / 2.,
+                Align::Right => room - width,
+            };
+            let y = i as f32 * line_height + metrics.ascent;
+
+            let mut prev = None;
+            for c in line.chars() {
+                if let Some(p) = prev {
+                    x += self.kerning(p, c, size);
+                }
+                glyphs.push(PositionedGlyph { c, x, y });
+                x += self.advance(c, size);
+                prev = Some(c);
+            }
+        }
+
+        let height = (lines.len() - 1) as f32 * line_height + metrics.height();
+        (glyphs, vec2(widest, height))
+    }
+
+    pub fn measure(&self, text: &str, size: f32) -> Vec2 {
+        self.layout(text, size, None, 1., Align::Left).1
+    }
+
+    fn key(&self, c: char, size: f32) -> (char, u32) {
+        match self.source {
+            Source::Outline(_) => (c, size.round().max(1.) as u32),
+            Source::Bitmap => {
+                let c = if (' '..='~').contains(&c) { c } else { '?' };
+                (c, DEBUG_HEIGHT)
+            }
+        }
+    }
+
+    // rasterizes the glyph if it is not cached, gives back the glyph and how much to scale it
+    pub(crate) fn glyph(&mut self, c: char, size: f32) -> (Glyph, f32) {
+        let key = self.key(c, size);
+        let scale = size / key.1 as f32;
+
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return (*glyph, scale);
+        }
+
+        let (width, height, offset, coverage) = match &self.source {
+            Source::Outline(font) => {
+                let (m, coverage) = font.rasterize(key.0, key.1 as f32);
+                let offset = (m.xmin as f32, -(m.ymin as f32 + m.height as f32));
+                (m.width as u32, m.height as u32, offset, coverage)
+            }
+            Source::Bitmap => {
+                let index = key.0 as u32 - ' ' as u32;
+                let (col, row) = (index % DEBUG_COLUMNS, index / DEBUG_COLUMNS);
+
+                let mut coverage = Vec::with_capacity((DEBUG_WIDTH * DEBUG_HEIGHT) as usize);
+                for y in 0..DEBUG_HEIGHT {
+                    let byte =
+                        DEBUG_FONT[((row * DEBUG_HEIGHT + y) * DEBUG_COLUMNS + col) as usize];
+                    for x in 0..DEBUG_WIDTH {
+                        coverage.push(if byte >> (7 - x) & 1 == 1 { 255 } else { 0 });
+                    }
+                }
+                let offset = (0., -(DEBUG_BASELINE as f32));
+                (DEBUG_WIDTH, DEBUG_HEIGHT, offset, coverage)
+            }
+        };
+
+        let glyph = self.place(width, height, offset, &coverage);
+        self.glyphs.insert(key, glyph);
+        (glyph, scale)
+    }
+
+    fn place(&mut self, width: u32, height: u32, offset: (f32, f32), coverage: &[u8]) -> Glyph {
+        let padded = (width + GLYPH_PADDING * 2, height + GLYPH_PADDING * 2);
+
+        let found = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .find_map(|(i, page)| page.packer.pack(padded.0, padded.1).map(|rect| (i, rect)));
+        let (page, rect) = match found {
+            Some(found) => found,
+            None => {
+                let size = PAGE_SIZE.max(padded.0).max(padded.1);
+                let mut packer = SkylinePacker::new(size, size);
+                let rect = packer.pack(padded.0, padded.1).unwrap();
+                self.pages.push(GlyphPage {
+                    packer,
+                    image: RgbaImage::from_pixel(size, size, Rgba([255, 255, 255, 0])),
+                    texture: None,
+                    dirty: true,
+                });
+                (self.pages.len() - 1, rect)
+            }
+        };
+
+        let (x, y) = (rect.x + GLYPH_PADDING, rect.y + GLYPH_PADDING);
+        self.pages[page].dirty = true;
+        let image = &mut self.pages[page].image;
+        for gy in 0..height {
+            for gx in 0..width {
+                let alpha = coverage[(gy * width + gx) as usize];
+                image.put_pixel(x + gx, y + gy, Rgba([255, 255, 255, alpha]));
+            }
+        }
+
+        let (pw, ph) = image.dimensions();
+        Glyph {
+            page,
+            uv: UvRect::from_pixels(x, y, width, height, pw, ph),
+            offset,
+            width,
+            height,
+        }
+    }
+
+    // sends any newly rasterized glyphs to the gpu
+    pub(crate) fn upload(&mut self) {
+        let nearest = self.is_bitmap();
+        for page in &mut self.pages {
+            if !page.dirty {
+                continue;
+            }
+            match &page.texture {
+                Some(texture) => texture.upload(&page.image),
+                None => {
+                    let texture = Texture::from_image(&page.image);
+                    texture.set_filter(nearest);
+                    page.texture = Some(texture);
+                }
+            }
+            page.dirty = false;
+        }
+    }
+
+    // a solid block on the glyph pages so untextured shapes can share the glyphs' draw call
+    pub(crate) fn white_texel(&mut self) -> Glyph {
+        if let Some(white) = self.white {
+            return white;
+        }
+
+        let mut white = self.place(3, 3, (0., 0.), &[255; 9]);
+        let (u, v) = (
+            (white.uv.u0 + white.uv.u1) / 2.,
+            (white.uv.v0 + white.uv.v1) / 2.,
+        );
+        white.uv = UvRect::new(u, v, u, v);
+        self.white = Some(white);
+        white
+    }
+
+    pub(crate) fn page_texture(&self, page: usize) -> Option<&Texture> {
+        self.pages.get(page)?.texture.as_ref()
+    }
+
+    pub fn pages(&self) -> impl Iterator<Item = &RgbaImage> {
+        self.pages.iter().map(|p| &p.image)
+    }
+}
+
+// a block of text drawn through a sprite batch, position is the top left of the first line
+// (or of the wrap box), sizes are in pixels
+#[derive(Debug, Clone)]
+pub struct Text {
+    pub string: String,
+    pub position: Vec2,
+    pub size: f32,
+    pub color: Color,
+    pub align: Align,
+    pub wrap_width: Option<f32>,
+    // multiplies the font's line height
+    pub line_spacing: f32,
+    pub depth: f32,
+}
+
+impl Text {
+    pub fn new(string: &str, position: Vec2, size: f32) -> Self {
+        Self {
+            string: string.to_string(),
+            position,
+            size,
+            color: Color::WHITE,
+            align: Align::Left,
+            wrap_width: None,
+            line_spacing: 1.,
+            depth: 0.,
+        }
+    }
+
+    pub fn layout(&self, font: &Font) -> (Vec<PositionedGlyph>, Vec2) {
+        font.layout(
+            &self.string,
+            self.size,
+            self.wrap_width,
+            self.line_spacing,
+            self.align,
+        )
+    }
+
+    pub fn measure(&self, font: &Font) -> Vec2 {
+        self.layout(font).1
+    }
+
+    // the font's glyph pages have to stay alive until the batch is flushed
+    pub fn draw(&self, font: &mut Font, batch: &mut SpriteBatch) {
+        let (positioned, _) = self.layout(font);
+
+        let glyphs: Vec<_> = positioned
+            .iter()
+            .map(|p| (p, font.glyph(p.c, self.size)))
+            .collect();
+        font.upload();
+
+        for (p, (glyph, scale)) in glyphs {
+            if glyph.width == 0 || glyph.height == 0 {
+                continue;
+            }
+
+            // whole pixels keep small outline text sharp
+            let x = (self.position[X] + p.x + glyph.offset.0 * scale).round();
+            let y = (self.position[Y] + p.y + glyph.offset.1 * scale).round();
+
+            let mut sprite = Sprite::new(
+                vec2(x, y),
+                vec2(glyph.width as f32 * scale, glyph.height as f32 * scale),
+            );
+            sprite.uv = glyph.uv;
+            sprite.tint = self.color;
+            sprite.depth = self.depth;
+
+            let texture = font.page_texture(glyph.page).unwrap();
+            batch.draw(texture, &sprite);
+        }
+    }
+}
+
+#[cfg(test)]
+fn xy(v: Vec2) -> (f32, f32) {
+    (v[X], v[Y])
+}
+
+#[test]
+fn test_debug_font_measure() {
+    let font = Font::debug();
+
+    assert_eq!(xy(font.measure("Hi", 13.)), (16., 13.));
+    assert_eq!(xy(font.measure("Hi\nthere", 26.)), (80., 52.));
+
+    let mut text = Text::new("ab\nc", vec2(0., 0.), 13.);
+    text.line_spacing = 2.;
+    assert_eq!(xy(text.measure(&font)), (16., 39.));
+}
+
+#[test]
+fn test_wrap() {
+    let font = Font::debug();
+
+    // 8px per character at 13px
+    assert_eq!(
+        font.wrap("aaa bbb ccc", 13., Some(60.)),
+        vec!["aaa bbb", "ccc"]
+    );
+    assert_eq!(
+        font.wrap("abcdefgh", 13., Some(24.)),
+        vec!["abc", "def", "gh"]
+    );
+    assert_eq!(font.wrap("a\n\nb", 13., None), vec!["a", "", "b"]);
+}
+
+#[test]
+fn test_align() {
+    let font = Font::debug();
+
+    let (glyphs, size) = font.layout("ab\nc", 13., Some(40.), 1., Align::Right);
+    assert_eq!(xy(size), (16., 26.));
+    assert_eq!(
+        glyphs[0],
+        PositionedGlyph {
+            c: 'a',
+            x: 24.,
+            y: 10.
+        }
+    );
+    assert_eq!(
+        glyphs[2],
+        PositionedGlyph {
+            c: 'c',
+            x: 32.,
+            y: 23.
+        }
+    );
+
+    let (glyphs, _) = font.layout("ab", 13., None, 1., Align::Center);
+    assert_eq!(glyphs[0].x, -8.);
+}
+
+#[test]
+fn test_debug_glyph_raster() {
+    let mut font = Font::debug();
+
+    let (a, scale) = font.glyph('A', 26.);
+    assert_eq!((a.width, a.height, scale), (8, 13, 2.));
+
+    // the cross bar of the A from the raw font data, .######.
+    let page = font.pages().next().unwrap();
+    let x = (a.uv.u0 * page.width() as f32) as u32;
+    let y = (a.uv.v0 * page.height() as f32) as u32;
+    let bar: Vec<u8> = (0..8).map(|i| page.get_pixel(x + i, y + 7)[3]).collect();
+    assert_eq!(bar, vec![0, 255, 255, 255, 255, 255, 255, 0]);
+
+    // cached and anything outside ascii falls back to ?
+    let (again, _) = font.glyph('A', 13.);
+    assert_eq!(again.uv, a.uv);
+    assert_eq!(font.glyph('é', 13.).0.uv, font.glyph('?', 13.).0.uv);
+}
@@ -1,16 +1,24 @@
+use std::rc::Rc;
+
+use image::RgbaImage;
+
+use super::context::GlContext;
+
 pub struct Texture {
     id: u32,
     width: u32,
     height: u32,
+    ctx: Rc<GlContext>,
 }
 
 impl Texture {
     pub fn new() -> Self {
         let mut id = 0;
+        let ctx = GlContext::current();
 
         let (width, height) = unsafe {
             gl::GenTextures(1, &mut id);
-            gl::BindTexture(gl::TEXTURE_2D, id);
+            ctx.bind_texture(gl::TEXTURE_2D, id);
 
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
@@ -41,6 +49,117 @@ impl Texture {
             (width as u32, height as u32)
         };
 
-        Self { id, width, height }
+        Self {
+            id,
+            width,
+            height,
+            ctx,
+        }
+    }
+}
+
+impl Texture {
+    // an rgba8 texture with no data in it, used as a render target attachment
+    pub fn empty(width: u32, height: u32) -> Self {
+        let mut id = 0;
+        let ctx = GlContext::current();
+
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            ctx.bind_texture(gl::TEXTURE_2D, id);
+
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                width as i32,
+                height as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+        }
+
+        Self {
+            id,
+            width,
+            height,
+            ctx,
+        }
+    }
+
+    // row 0 of the image ends up at v = 0
+    pub fn from_image(image: &RgbaImage) -> Self {
+        let (width, height) = image.dimensions();
+        let texture = Self::empty(width, height);
+        texture.upload(image);
+        texture
+    }
+
+    // replaces the whole texture, the image has to be the same size as it
+    pub fn upload(&self, image: &RgbaImage) {
+        assert_eq!(
+            image.dimensions(),
+            (self.width, self.height),
+            "Uploaded image is not the size of the texture"
+        );
+
+        self.ctx.bind_texture(gl::TEXTURE_2D, self.id);
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                0,
+                0,
+                self.width as i32,
+                self.height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                image.as_raw().as_ptr().cast(),
+            );
+        }
+    }
+
+    pub fn from_file(path: &str) -> Self {
+        let image = image::open(path)
+            .expect("Could not open the texture image")
+            .into_rgba8();
+        Self::from_image(&image)
+    }
+
+    // nearest keeps pixel art sharp, linear blends between texels
+    pub fn set_filter(&self, nearest: bool) {
+        let filter = if nearest { gl::NEAREST } else { gl::LINEAR } as i32;
+        self.ctx.bind_texture(gl::TEXTURE_2D, self.id);
+        unsafe {
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter);
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn bind(&self, unit: u32) {
+        self.ctx.active_texture(unit);
+        self.ctx.bind_texture(gl::TEXTURE_2D, self.id);
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        self.ctx.delete_texture(self.id);
     }
 }
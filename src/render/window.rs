@@ -1,15 +1,26 @@
 use std::{
+    cell::RefCell,
     ops::{Deref, DerefMut},
+    rc::Rc,
     sync::mpsc::Receiver,
 };
 
 pub use glfw::Context;
 pub use glfw::Key;
 pub use glfw::WindowEvent;
-use glfw::{Action, FlushedMessages, Window as GlfwWindow, WindowHint, WindowMode, FAIL_ON_ERRORS};
+use glfw::{
+    Action, ContextCreationApi, FlushedMessages, Window as GlfwWindow, WindowHint, WindowMode,
+    FAIL_ON_ERRORS,
+};
 
 use crate::math::color::{Color, Format};
 
+use image::RgbaImage;
+
+use super::context::GlContext;
+use super::cursor::{Cursor, CursorMode};
+use super::readback;
+
 pub struct FrameData {
     pub time: f64,
     pub events: WindowEvent,
@@ -19,10 +30,197 @@ pub struct EventIter<'a> {
     events: &'a mut FlushedMessages<'a, (f64, WindowEvent)>,
 }
 
-#[derive(Debug)]
+// sizes are split because on HiDPI screens the window is measured in screen coordinates
+// but the framebuffer (what gl::Viewport wants) is measured in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resize {
+    pub window_size: (i32, i32),
+    pub framebuffer_size: (i32, i32),
+    pub content_scale: (f32, f32),
+}
+
+pub type ResizeCallback = Box<dyn FnMut(&Resize)>;
+
+// a hidden window that never gets shown, it only exists so its context can own the gl
+// objects that every other window shares. Its kept alive by every window made from it
+struct ShareGroup {
+    root: GlfwWindow,
+    _events: Receiver<(f64, WindowEvent)>,
+}
+
+impl Drop for ShareGroup {
+    fn drop(&mut self) {
+        GlContext::retire(self.root.window_ptr() as usize);
+    }
+}
+
+// owns glfw and the shared gl context, make one of these and create all the windows from it
+// so glfw is only initialized once and the gl function pointers are only loaded once
+pub struct Glfw {
+    glfw: glfw::Glfw,
+    group: Rc<ShareGroup>,
+}
+
+thread_local! {
+    // what Window::new and Window::headless make their windows with, made on first use
+    static SHARED: RefCell<Option<Glfw>> = const { RefCell::new(None) };
+    static SHARED_HEADLESS: RefCell<Option<Glfw>> = const { RefCell::new(None) };
+}
+
+impl Glfw {
+    pub fn new() -> Self {
+        Self::init(false)
+    }
+
+    // every window is created hidden, render into a RenderTarget and read the pixels back.
+    // set QRGL_CONTEXT_API to egl or osmesa to pick how the context gets made. glfw still
+    // wants a display for this, with no display at all use HeadlessContext from the egl
+    // feature
+    pub fn headless() -> Self {
+        Self::init(true)
+    }
+
+    fn init(headless: bool) -> Self {
+        let mut glfw = glfw::init(FAIL_ON_ERRORS).expect("Failed to init glfw");
+        Self::context_hints(&mut glfw);
+
+        if headless {
+            if let Some(api) = Self::context_api_from_env() {
+                glfw.window_hint(WindowHint::ContextCreationApi(api));
+            }
+        }
+
+        glfw.window_hint(WindowHint::Visible(false));
+        let (mut root, events) = glfw
+            .create_window(1, 1, "QRGL Shared Context", WindowMode::Windowed)
+            .expect("Failed to make the shared context");
+
+        // headless leaves the hint on so every window made after stays hidden too
+        if !headless {
+            glfw.window_hint(WindowHint::Visible(true));
+        }
+
+        root.make_current();
+        gl::load_with(|s| root.get_proc_address(s));
+        GlContext::register(root.window_ptr() as usize, None);
+
+        Self {
+            glfw,
+            group: Rc::new(ShareGroup {
+                root,
+                _events: events,
+            }),
+        }
+    }
+
+    // a typo would quietly test another backend, so it fails instead of falling back
+    fn context_api_from_env() -> Option<ContextCreationApi> {
+        match std::env::var("QRGL_CONTEXT_API")
+            .ok()?
+            .to_lowercase()
+            .as_str()
+        {
+            "native" => Some(ContextCreationApi::Native),
+            "egl" => Some(ContextCreationApi::Egl),
+            "osmesa" => Some(ContextCreationApi::OsMesa),
+            other => panic!("Unknown QRGL_CONTEXT_API: {other}, expected native, egl or osmesa"),
+        }
+    }
+
+    fn with_shared<R>(headless: bool, f: impl FnOnce(&Glfw) -> R) -> R {
+        let shared = if headless { &SHARED_HEADLESS } else { &SHARED };
+        shared.with(|shared| {
+            let mut shared = shared.borrow_mut();
+            f(shared.get_or_insert_with(|| Self::init(headless)))
+        })
+    }
+
+    fn context_hints(glfw: &mut glfw::Glfw) {
+        // every context in the share group has to be made with the same version
+        glfw.window_hint(WindowHint::ContextVersionMajor(3));
+        glfw.window_hint(WindowHint::ContextVersionMinor(3));
+    }
+
+    // the new window is made current, so anything created right after goes into its context
+    pub fn create_window(&self, title: &str, width: u32, height: u32) -> Window {
+        GlContext::flush_current();
+        let (mut w, e) = self
+            .group
+            .root
+            .create_shared(width, height, title, WindowMode::Windowed)
+            .expect("Failed to make a window");
+
+        w.make_current();
+        GlContext::register(
+            w.window_ptr() as usize,
+            Some(self.group.root.window_ptr() as usize),
+        );
+        w.set_all_polling(true);
+
+        let (fb_w, fb_h) = w.get_framebuffer_size();
+        unsafe {
+            gl::Viewport(0, 0, fb_w, fb_h);
+        }
+
+        Window {
+            handle: w,
+            events: e,
+            resize_callbacks: Vec::new(),
+            last_cursor_pos: None,
+            mouse_delta: (0.0, 0.0),
+            cursor_mode: CursorMode::Normal,
+            pending_viewport: None,
+            _group: self.group.clone(),
+        }
+    }
+
+    pub fn poll_events(&mut self) {
+        self.glfw.poll_events();
+    }
+
+    pub fn wait_events(&mut self) {
+        self.glfw.wait_events();
+    }
+}
+
+impl Default for Glfw {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct Window {
     handle: GlfwWindow,
     events: Receiver<(f64, WindowEvent)>,
+    resize_callbacks: Vec<ResizeCallback>,
+    last_cursor_pos: Option<(f64, f64)>,
+    mouse_delta: (f64, f64),
+    // what set_cursor_mode was asked for, glfw cant tell locked apart from disabled with raw
+    // motion turned on by hand
+    cursor_mode: CursorMode,
+    // a resize that came in while another window was current, applied in make_current
+    pending_viewport: Option<(i32, i32)>,
+    _group: Rc<ShareGroup>,
+}
+
+// the context goes with the window, so there is no deleting its vaos and framebuffers after.
+// shared objects dropped later wait for another window of the group
+impl Drop for Window {
+    fn drop(&mut self) {
+        if self.handle.is_current() {
+            GlContext::current().flush_deletes();
+        }
+        GlContext::retire(self.handle.window_ptr() as usize);
+    }
+}
+
+impl std::fmt::Debug for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Window")
+            .field("handle", &self.handle)
+            .field("resize_callbacks", &self.resize_callbacks.len())
+            .finish()
+    }
 }
 
 impl Deref for Window {
@@ -46,24 +244,124 @@ impl Default for Window {
 }
 
 impl Window {
+    // every window made this way on a thread shares one Glfw and its objects, make your own
+    // Glfw and use create_window to keep a set of windows apart
     pub fn new(title: &str, width: u32, height: u32) -> Self {
-        let mut glfw = glfw::init(FAIL_ON_ERRORS).expect("Failed to init glfw");
-        glfw.window_hint(WindowHint::ContextVersionMajor(3));
-        glfw.window_hint(WindowHint::ContextVersionMinor(3));
+        Glfw::with_shared(false, |glfw| glfw.create_window(title, width, height))
+    }
 
-        let (mut w, e) = glfw
-            .create_window(width, height, title, WindowMode::Windowed)
-            .expect("Failed to make a window");
+    // a hidden window for tests and tools, see Glfw::headless
+    pub fn headless(width: u32, height: u32) -> Self {
+        Glfw::with_shared(true, |glfw| {
+            glfw.create_window("QRGL Headless", width, height)
+        })
+    }
 
-        w.make_current();
-        w.set_all_polling(true);
+    // each window has its own context, call this before drawing into a different window
+    pub fn make_current(&mut self) {
+        // the vaos and framebuffers the last context dropped can only go while its current
+        GlContext::flush_current();
+        self.handle.make_current();
+        GlContext::current().flush_deletes();
 
-        gl::load_with(|s| w.get_proc_address(s));
+        if let Some((w, h)) = self.pending_viewport.take() {
+            unsafe {
+                gl::Viewport(0, 0, w, h);
+            }
+        }
+    }
 
-        Self {
-            handle: w,
-            events: e,
+    // shows the frame, gl objects dropped since the last one get deleted here
+    pub fn swap_buffers(&mut self) {
+        self.handle.swap_buffers();
+        GlContext::current().flush_deletes();
+    }
+
+    pub fn is_current(&self) -> bool {
+        self.handle.is_current()
+    }
+
+    pub fn resize_state(&self) -> Resize {
+        Resize {
+            window_size: self.handle.get_size(),
+            framebuffer_size: self.handle.get_framebuffer_size(),
+            content_scale: self.handle.get_content_scale(),
+        }
+    }
+
+    // called after the viewport has been updated, nothing is drawn for you here. when another
+    // window is current the viewport waits for this one's make_current. cameras, render
+    // targets and post processing follow along if they are shared with the callback:
+    //
+    //     let post = Rc::new(RefCell::new(PostProcess::new(w, h)));
+    //     let follow = post.clone();
+    //     window.on_resize(move |resize| follow.borrow_mut().follow(resize));
+    pub fn on_resize<F: FnMut(&Resize) + 'static>(&mut self, callback: F) {
+        self.resize_callbacks.push(Box::new(callback));
+    }
+
+    fn handle_resize(&mut self) {
+        let resize = self.resize_state();
+        let (w, h) = resize.framebuffer_size;
+
+        // minimized windows report a 0x0 framebuffer, keep the old viewport around
+        if w > 0 && h > 0 {
+            // the viewport belongs to whatever context is current, which might not be ours
+            if self.handle.is_current() {
+                unsafe {
+                    gl::Viewport(0, 0, w, h);
+                }
+            } else {
+                self.pending_viewport = Some((w, h));
+            }
+        }
+
+        for callback in &mut self.resize_callbacks {
+            callback(&resize);
+        }
+    }
+
+    pub fn set_cursor_mode(&mut self, mode: CursorMode) {
+        self.handle.set_cursor_mode(mode.into());
+
+        let raw = mode == CursorMode::Locked && self.handle.glfw.supports_raw_motion();
+        self.handle.set_raw_mouse_motion(raw);
+        self.cursor_mode = mode;
+
+        // the cursor jumps when the mode changes, dont count that as movement
+        self.last_cursor_pos = None;
+    }
+
+    pub fn cursor_mode(&self) -> CursorMode {
+        self.cursor_mode
+    }
+
+    // raw motion only works while the cursor is disabled, returns false if the platform cant do it
+    pub fn set_raw_mouse_motion(&mut self, enabled: bool) -> bool {
+        if enabled && !self.handle.glfw.supports_raw_motion() {
+            return false;
         }
+
+        self.handle.set_raw_mouse_motion(enabled);
+        true
+    }
+
+    // how far the mouse moved since the last call, collected while pumping Window::events
+    pub fn take_mouse_delta(&mut self) -> (f64, f64) {
+        std::mem::take(&mut self.mouse_delta)
+    }
+
+    // pass None to go back to the default arrow
+    pub fn set_cursor(&mut self, cursor: Option<Cursor>) {
+        self.handle.set_cursor(cursor.map(|c| c.handle));
+    }
+
+    pub fn clipboard(&self) -> Option<String> {
+        self.handle.get_clipboard_string()
+    }
+
+    pub fn set_clipboard(&mut self, text: &str) {
+        self.handle.set_clipboard_string(text);
     }
 
     pub fn events(&mut self) -> Option<FrameData> {
@@ -71,12 +369,18 @@ impl Window {
             Ok(data) => {
                 let (dt, event) = data;
 
-                if let WindowEvent::Size(x, y) = event {
-                    unsafe {
-                        gl::Viewport(0, 0, x, y);
-                        self.clear(Color::GREEN);
-                        self.swap_buffers();
+                // the window size event is still passed through, but the viewport follows the
+                // framebuffer because thats what is actually in pixels
+                if let WindowEvent::FramebufferSize(..) | WindowEvent::ContentScale(..) = event {
+                    self.handle_resize();
+                }
+
+                if let WindowEvent::CursorPos(x, y) = event {
+                    if let Some((lx, ly)) = self.last_cursor_pos {
+                        self.mouse_delta.0 += x - lx;
+                        self.mouse_delta.1 += y - ly;
                     }
+                    self.last_cursor_pos = Some((x, y));
                 }
 
                 Some(FrameData {
@@ -92,18 +396,21 @@ impl Window {
     }
 
     pub fn clear(&self, color: Color) {
+        let [r, g, b, a] = color.to_rgba_f32();
         unsafe {
-            match color.format {
-                Format::RGB { r, g, b } => {
-                    gl::ClearColor(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0);
-                }
-                Format::RGBA { r, g, b, a } => todo!(),
-                Format::SRGB => todo!(),
-            }
-
+            gl::ClearColor(r, g, b, a);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
     }
+
+    // reads the back buffer, so call it after drawing and before swap_buffers
+    pub fn screenshot(&self) -> RgbaImage {
+        readback::read_pixels(self)
+    }
+
+    pub fn save_screenshot<P: AsRef<std::path::Path>>(&self, path: P) -> image::ImageResult<()> {
+        readback::save_image(&self.screenshot(), path)
+    }
 }
 
 pub trait InputEvents {
@@ -1,4 +1,7 @@
-use qrgl::{math::color::*, math::vector::*, render::ogl::*, render::shapes::*, window::*};
+use qrgl::{
+    math::color::*, math::transform::*, math::vector::*, render::ogl::*, render::shapes::*,
+    window::*,
+};
 
 fn main() {
     let mut window = Window::default();
@@ -42,7 +45,7 @@ fn main() {
         window.clear(Color::rgb(100, 149, 237));
 
         for d in &draw_streams {
-            d.0.draw(&d.1);
+            d.0.draw(&d.1, &Transform2D::default());
         }
 
         window.swap_buffers();